@@ -0,0 +1,162 @@
+use chrono::NaiveDate;
+use stable_eyre::eyre::*;
+
+use crate::amounts::clean_amount;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// Readers configured by the user (toml profiles, scripts) name the kind of each action
+// with a string such as "Buy" or "Div" instead of building QifActions themselves.
+// The fields not needed by a kind are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ActionFields {
+    pub date: NaiveDate,
+    pub payee: String,
+    pub memo: Option<String>,
+    pub category: Option<String>,
+    pub amount: String,
+    pub symbol: String,
+    pub security_name: Option<String>,
+    pub quantity: String,
+    pub price: String,
+    pub fees: String,
+}
+
+pub const ACTION_KINDS: &[&str] = &[
+    "Generic", "Buy", "Sell", "ShtSell", "CvrShrt", "Div", "IntInc", "MiscInc", "CGLong",
+    "CGShort", "MargInt", "ShrsIn", "ShrsOut", "Skip",
+];
+
+fn unsigned(amount: &str) -> String {
+    clean_amount(amount).trim_start_matches('-').to_string()
+}
+
+fn optional(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(unsigned(value))
+    }
+}
+
+impl ActionFields {
+    fn to_trade(&self) -> Trade {
+        Trade {
+            date: self.date,
+            symbol: self.symbol.clone(),
+            price: unsigned(&self.price),
+            quantity: unsigned(&self.quantity),
+            amount: unsigned(&self.amount),
+            fees: unsigned(&self.fees),
+            memo: self.memo.clone(),
+        }
+    }
+
+    // securities are entered as stocks, quicken lets the type be changed later.
+    fn enter_symbol(&self, symbols: &mut Option<Symbols>) -> Result<()> {
+        let symbols = symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        if self.symbol.is_empty() {
+            return Err(eyre!("No symbol found for {:?}", self));
+        }
+        let name = self.security_name.as_ref().unwrap_or(&self.symbol);
+        symbols.enter_if_not_found(&self.symbol, name, &SecurityType::Stock)
+    }
+}
+
+pub fn build_actions(
+    kind: &str,
+    fields: &ActionFields,
+    symbols: &mut Option<Symbols>,
+) -> Result<Vec<QifAction>> {
+    let mut res: Vec<QifAction> = Vec::new();
+    let date = fields.date;
+    match kind {
+        "Skip" => {}
+        "Generic" => {
+            res.push(QifAction::Generic {
+                date,
+                payee: fields.payee.clone(),
+                memo: fields.memo.clone(),
+                category: fields.category.clone(),
+                amount: clean_amount(&fields.amount),
+            });
+        }
+        "MargInt" => {
+            res.push(QifAction::MargInt {
+                date,
+                memo: fields.memo.clone().unwrap_or_else(|| fields.payee.clone()),
+                amount: unsigned(&fields.amount),
+            });
+        }
+        _ => {
+            fields.enter_symbol(symbols)?;
+            let symbol = fields.symbol.clone();
+            let amount = clean_amount(&fields.amount);
+            match kind {
+                "Buy" => res.push(QifAction::Buy {
+                    trade: fields.to_trade(),
+                }),
+                "Sell" => res.push(QifAction::Sell {
+                    trade: fields.to_trade(),
+                }),
+                "ShtSell" => res.push(QifAction::ShtSell {
+                    trade: fields.to_trade(),
+                }),
+                "CvrShrt" => res.push(QifAction::CvrShrt {
+                    trade: fields.to_trade(),
+                }),
+                "Div" => res.push(QifAction::Div {
+                    date,
+                    symbol,
+                    amount,
+                }),
+                "IntInc" => res.push(QifAction::IntInc {
+                    date,
+                    symbol,
+                    amount,
+                }),
+                "CGLong" => res.push(QifAction::CGLong {
+                    date,
+                    symbol,
+                    amount,
+                }),
+                "CGShort" => res.push(QifAction::CGShort {
+                    date,
+                    symbol,
+                    amount,
+                }),
+                "MiscInc" => res.push(QifAction::MiscInc {
+                    date,
+                    symbol,
+                    memo: fields.memo.clone().unwrap_or_default(),
+                    amount,
+                }),
+                "ShrsIn" => res.push(QifAction::ShrsIn {
+                    date,
+                    symbol,
+                    quantity: unsigned(&fields.quantity),
+                    price: optional(&fields.price),
+                    amount: optional(&fields.amount),
+                }),
+                "ShrsOut" => res.push(QifAction::ShrsOut {
+                    date,
+                    symbol,
+                    quantity: unsigned(&fields.quantity),
+                    price: optional(&fields.price),
+                    amount: optional(&fields.amount),
+                }),
+                _ => {
+                    return Err(eyre!(
+                        "Unrecognized action kind \"{}\", expected one of {:?}",
+                        kind,
+                        ACTION_KINDS
+                    ))
+                }
+            }
+        }
+    };
+    Ok(res)
+}
@@ -0,0 +1,163 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, parse_amount};
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// American Express card export, with "Include all additional transaction details" checked.
+//
+// Amex writes charges as positive and credits as negative amounts, the opposite of what
+// quicken expects for !Type:CCard, so signs are inverted.
+//
+// When card_member_class is set each transaction is tagged with the card member as a
+// quicken class, so that the category becomes "<category>/<card member>".
+pub struct AmexReader {
+    pub card_member_class: bool,
+}
+
+impl Reader for AmexReader {
+    fn csv_header(&self) -> String {
+        r#"Date,Description,Card Member,Account #,Amount,Extended Details,Appears On Your Statement As,Address,City/State,Zip Code,Country,Reference,Category"#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        _securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        let mut rdr = csv::Reader::from_reader(bufreader);
+        for record in rdr.deserialize::<AmexTransaction>() {
+            if record.is_err() {
+                // some csv files are not too clean.
+                break;
+            }
+            qif_actions.push(record?.to_qif_action(self.card_member_class)?);
+        }
+
+        // reversing because amex lists newest transactions first.
+        qif_actions.reverse();
+        Ok(qif_actions)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmexTransaction {
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Card Member")]
+    pub card_member: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Extended Details")]
+    pub extended_details: String,
+    #[serde(rename = "Appears On Your Statement As")]
+    pub statement_descriptor: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+}
+
+impl AmexTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from amex: {}", &self.date))
+    }
+
+    // extended details span several lines but a qif memo has to fit on one.
+    fn memo(&self) -> Option<String> {
+        let memo = self
+            .extended_details
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if memo.is_empty() {
+            None
+        } else {
+            Some(memo)
+        }
+    }
+
+    fn category(&self, card_member_class: bool) -> Option<String> {
+        if card_member_class && !self.card_member.is_empty() {
+            Some(format!("{}/{}", self.category, self.card_member.trim()))
+        } else if self.category.is_empty() {
+            None
+        } else {
+            Some(self.category.clone())
+        }
+    }
+
+    fn to_qif_action(&self, card_member_class: bool) -> Result<QifAction> {
+        let payee = if self.statement_descriptor.is_empty() {
+            self.description.clone()
+        } else {
+            self.statement_descriptor.clone()
+        };
+        Ok(QifAction::Generic {
+            date: self.get_date()?,
+            payee,
+            memo: self.memo(),
+            category: self.category(card_member_class),
+            amount: format_amount(-parse_amount(&self.amount)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_amex() -> Result<()> {
+        let csv = AmexReader {
+            card_member_class: true,
+        }
+        .csv_header()
+            + "\n"
+            + "01/16/2024,AUTOPAY PAYMENT,JANE DOE,-12345,-500.00,\"AUTOPAY\",AUTOPAY PAYMENT,,,,,'1',\n"
+            + "01/15/2024,UBER TRIP,JOHN DOE,-12345,23.45,\"UBER TRIP\nHELP.UBER.COM\",UBER   *TRIP,1455 MARKET ST,\"SAN FRANCISCO\nCA\",94103,UNITED STATES,'2',Transportation-Taxis & Coach\n";
+
+        let reader = AmexReader {
+            card_member_class: true,
+        };
+        let actions = reader.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 2);
+        match &actions[0] {
+            QifAction::Generic {
+                payee,
+                memo,
+                category,
+                amount,
+                ..
+            } => {
+                assert_eq!(payee, "UBER   *TRIP");
+                assert_eq!(memo.as_deref(), Some("UBER TRIP HELP.UBER.COM"));
+                assert_eq!(
+                    category.as_deref(),
+                    Some("Transportation-Taxis & Coach/JOHN DOE")
+                );
+                assert_eq!(amount, "-23.45");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::Generic {
+                category, amount, ..
+            } => {
+                assert_eq!(category.as_deref(), Some("/JANE DOE"));
+                assert_eq!(amount, "500.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+}
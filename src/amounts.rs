@@ -0,0 +1,78 @@
+use stable_eyre::eyre::*;
+
+// Amounts are carried around as strings, exactly as they appear in the csv.
+// These helpers are for the few places where arithmetic has to be done on them.
+
+// remove dollar signs and thousands separators, and turn accounting style negative
+// amounts such as "($12.34)" into "-12.34".
+pub fn clean_amount(amount: &str) -> String {
+    let cleaned: String = amount
+        .trim()
+        .chars()
+        .filter(|c| *c != '$' && *c != ',')
+        .collect();
+    match cleaned
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        Some(negative) => "-".to_string() + negative,
+        None => cleaned,
+    }
+}
+
+// parse an amount such as "$1,234.56", "($12.34)" or "-12.5".   An empty field is zero.
+pub fn parse_amount(amount: &str) -> Result<f64> {
+    let cleaned = clean_amount(amount);
+    if cleaned.is_empty() {
+        return Ok(0.0);
+    }
+    cleaned
+        .parse::<f64>()
+        .with_context(|| format!("Could not parse amount : {}", amount))
+}
+
+// format an amount of money with two decimal places.
+pub fn format_amount(amount: f64) -> String {
+    format!("{:.2}", amount)
+}
+
+// format a quantity or price without losing precision and without trailing zeros.
+pub fn format_quantity(quantity: f64) -> String {
+    let formatted = format!("{:.8}", quantity);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    if trimmed == "-0" {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_amount() {
+        assert_eq!(clean_amount("$1,234.56"), "1234.56");
+        assert_eq!(clean_amount("($12.34)"), "-12.34");
+        assert_eq!(clean_amount(""), "");
+    }
+
+    #[test]
+    fn test_parse_amount() -> Result<()> {
+        assert_eq!(parse_amount("($1,012.34)")?, -1012.34);
+        assert_eq!(parse_amount("$1,234.56")?, 1234.56);
+        assert_eq!(parse_amount("-12.5")?, -12.5);
+        assert_eq!(parse_amount(" ")?, 0.0);
+        assert!(parse_amount("abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_amount(1234.5), "1234.50");
+        assert_eq!(format_quantity(100.0), "100");
+        assert_eq!(format_quantity(0.12345678), "0.12345678");
+        assert_eq!(format_quantity(-2.5), "-2.5");
+    }
+}
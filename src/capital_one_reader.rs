@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+
+use crate::csv_reader::*;
+use crate::split_amount::*;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Capital One credit cards.
+pub struct CapitalOneReader;
+
+impl Reader for CapitalOneReader {
+    fn csv_header(&self) -> String {
+        r#"Transaction Date,Posted Date,Card No.,Description,Category,Debit,Credit"#.to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<CapitalOneTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapitalOneTransaction {
+    #[serde(rename = "Transaction Date")]
+    pub date: String,
+    #[serde(rename = "Card No.")]
+    pub card_number: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+    #[serde(rename = "Debit")]
+    pub debit: String,
+    #[serde(rename = "Credit")]
+    pub credit: String,
+}
+
+impl SplitAmountTransaction for CapitalOneTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from capital one: {}", &self.date))
+    }
+
+    fn payee(&self) -> String {
+        self.description.clone()
+    }
+
+    fn debit(&self) -> &str {
+        &self.debit
+    }
+
+    fn credit(&self) -> &str {
+        &self.credit
+    }
+
+    fn memo(&self) -> Option<String> {
+        Some(format!("Card {}", self.card_number))
+    }
+
+    fn category(&self) -> Option<String> {
+        if self.category.is_empty() {
+            None
+        } else {
+            Some(self.category.clone())
+        }
+    }
+}
+
+impl Transaction for CapitalOneTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        to_generic_action(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_capital_one() -> Result<()> {
+        let csv = CapitalOneReader {}.csv_header()
+            + "\n"
+            + "2024-01-17,2024-01-18,1234,CAPITAL ONE AUTOPAY PYMT,Payment/Credit,,500.00\n"
+            + "2024-01-16,,1234,PENDING AUTHORIZATION,Other,,\n"
+            + "2024-01-15,2024-01-16,1234,GROCER,Groceries,12.34,\n";
+        let actions = CapitalOneReader {}.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        match &actions[..] {
+            [QifAction::Generic {
+                memo,
+                category,
+                amount: debit,
+                ..
+            }, QifAction::Generic { amount: credit, .. }] => {
+                assert_eq!(memo.as_deref(), Some("Card 1234"));
+                assert_eq!(category.as_deref(), Some("Groceries"));
+                assert_eq!(debit, "-12.34");
+                assert_eq!(credit, "500.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
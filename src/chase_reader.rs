@@ -0,0 +1,229 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Chase checking and savings accounts.
+pub struct ChaseCheckingReader;
+
+impl Reader for ChaseCheckingReader {
+    fn csv_header(&self) -> String {
+        r#"Details,Posting Date,Description,Amount,Type,Balance,Check or Slip #"#.to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<ChaseCheckingTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaseCheckingTransaction {
+    #[serde(rename = "Details")]
+    pub details: String,
+    #[serde(rename = "Posting Date")]
+    pub date: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Check or Slip #")]
+    pub check_number: String,
+}
+
+impl Transaction for ChaseCheckingTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let memo = if self.check_number.is_empty() {
+            self.transaction_type.clone()
+        } else {
+            format!("{} #{}", self.details, self.check_number)
+        };
+        res.push(QifAction::Generic {
+            date: get_date(&self.date)?,
+            payee: self.description.clone(),
+            memo: Some(memo),
+            category: None,
+            amount: self.amount.clone(),
+        });
+        Ok(res)
+    }
+}
+
+// Chase credit cards (Sapphire, Freedom and so on).
+//
+// Chase writes charges as negative and payments and returns as positive amounts,
+// which is the sign convention quicken uses for !Type:CCard, so amounts pass through as is.
+pub struct ChaseCardReader;
+
+impl Reader for ChaseCardReader {
+    fn csv_header(&self) -> String {
+        r#"Transaction Date,Post Date,Description,Category,Type,Amount,Memo"#.to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<ChaseCardTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaseCardTransaction {
+    #[serde(rename = "Transaction Date")]
+    pub date: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+    #[serde(rename = "Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Memo")]
+    pub memo: String,
+}
+
+impl Transaction for ChaseCardTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let csv_type = self.transaction_type.as_str();
+        let category = match csv_type {
+            // payments have no category, quicken will ask for the account paid from.
+            "Payment" => None,
+            "Sale" | "Return" | "Fee" | "Adjustment" => {
+                Some(self.category.clone()).filter(|category| !category.is_empty())
+            }
+            _ => {
+                println!("Unrecognized type found in .CSV : \"{}\".", csv_type);
+                None
+            }
+        };
+
+        let memo = if self.memo.is_empty() {
+            self.transaction_type.clone()
+        } else {
+            format!("{} : {}", self.transaction_type, self.memo)
+        };
+        res.push(QifAction::Generic {
+            date: get_date(&self.date)?,
+            payee: self.description.clone(),
+            memo: Some(memo),
+            category,
+            amount: self.amount.clone(),
+        });
+        Ok(res)
+    }
+}
+
+fn get_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%m/%d/%Y")
+        .with_context(|| format!("Could not parse date from chase: {}", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_names::FileNames;
+    use crate::opt::AccountType;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_checking_trailing_comma() -> Result<()> {
+        let csv = ChaseCheckingReader {}.csv_header()
+            + "\n"
+            + "DEBIT,01/16/2024,\"ONLINE PAYMENT\",-25.00,ACH_DEBIT,975.00,,\n"
+            + "CHECK,01/15/2024,\"CHECK 101\",-100.00,CHECK_PAID,1000.00,101,\n";
+        let actions = ChaseCheckingReader {}.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 2);
+        match &actions[0] {
+            QifAction::Generic { memo, amount, .. } => {
+                assert_eq!(memo.as_deref(), Some("CHECK #101"));
+                assert_eq!(amount, "-100.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_card() -> Result<()> {
+        let csv = ChaseCardReader {}.csv_header()
+            + "\n"
+            + "01/20/2024,01/21/2024,Payment Thank You-Mobile,,Payment,500.00,\n"
+            + "01/18/2024,01/19/2024,AMAZON MKTPL,Shopping,Return,19.99,Order 1234\n"
+            + "01/15/2024,01/16/2024,STARBUCKS,Food & Drink,Sale,-5.75,\n";
+        let actions = ChaseCardReader {}.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 3);
+        match (&actions[0], &actions[1], &actions[2]) {
+            (
+                QifAction::Generic {
+                    memo,
+                    category,
+                    amount,
+                    ..
+                },
+                QifAction::Generic {
+                    memo: returned_memo,
+                    category: returned_category,
+                    amount: returned,
+                    ..
+                },
+                QifAction::Generic {
+                    category: payment_category,
+                    amount: payment,
+                    ..
+                },
+            ) => {
+                // charges negative, returns and payments positive, as chase writes them.
+                assert_eq!(memo.as_deref(), Some("Sale"));
+                assert_eq!(category.as_deref(), Some("Food & Drink"));
+                assert_eq!(amount, "-5.75");
+                assert_eq!(returned_memo.as_deref(), Some("Return : Order 1234"));
+                assert_eq!(returned_category.as_deref(), Some("Shopping"));
+                assert_eq!(returned, "19.99");
+                assert_eq!(payment_category, &None);
+                assert_eq!(payment, "500.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+
+        let workdir = tempfile::tempdir()?;
+        let file_names = FileNames {
+            transactions_qif: workdir.path().join("ccard_chase.qif"),
+            linked_cash_qif: workdir.path().join("linked_cash_chase.qif"),
+            securities_qif: workdir.path().join("securities_chase.qif"),
+            workdir: workdir.path().to_path_buf(),
+        };
+        let transactions = QifTransactions {
+            qif_actions: actions,
+            account_actions: Vec::new(),
+            currencies: HashMap::new(),
+            account_type: AccountType::CreditCard,
+            symbols: None,
+        };
+        transactions.print_transactions(&file_names, &None)?;
+        let qif = std::fs::read_to_string(&file_names.transactions_qif)?;
+        assert!(qif.starts_with("!Type:CCard\n"));
+        assert!(qif.contains("T-5.75\n"));
+        assert!(qif.contains("LFood & Drink\n"));
+        assert!(!file_names.linked_cash_qif.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::csv_reader::*;
+use crate::split_amount::*;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Citi credit cards.
+pub struct CitiReader;
+
+impl Reader for CitiReader {
+    fn csv_header(&self) -> String {
+        r#"Status,Date,Description,Debit,Credit"#.to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<CitiTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CitiTransaction {
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Debit")]
+    pub debit: String,
+    #[serde(rename = "Credit")]
+    pub credit: String,
+}
+
+impl SplitAmountTransaction for CitiTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from citi: {}", &self.date))
+    }
+
+    fn payee(&self) -> String {
+        self.description.clone()
+    }
+
+    fn debit(&self) -> &str {
+        &self.debit
+    }
+
+    fn credit(&self) -> &str {
+        &self.credit
+    }
+}
+
+impl Transaction for CitiTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        if self.status == "Pending" {
+            // pending transactions may still change, they will be in a later download.
+            println!("Skipping pending transaction : {:#?}", self);
+            return Ok(Vec::new());
+        }
+        to_generic_action(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_citi() -> Result<()> {
+        let csv = CitiReader {}.csv_header()
+            + "\n"
+            + "Pending,01/18/2024,COFFEE,4.50,\n"
+            + "Cleared,01/17/2024,ONLINE PAYMENT,,-250.00\n"
+            + "Cleared,01/16/2024,ZERO DOLLAR VERIFICATION,,\n"
+            + "Cleared,01/15/2024,GROCER,12.34,\n";
+        let actions = CitiReader {}.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        match &actions[..] {
+            [QifAction::Generic {
+                payee,
+                amount: debit,
+                ..
+            }, QifAction::Generic { amount: credit, .. }] => {
+                assert_eq!(payee, "GROCER");
+                assert_eq!(debit, "-12.34");
+                // citi writes credits as negative numbers.
+                assert_eq!(credit, "250.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
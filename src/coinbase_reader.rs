@@ -0,0 +1,256 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{clean_amount, format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Coinbase "Transaction History" report.
+//
+// Each asset is entered as a Crypto security under its ticker.   Quantities are copied
+// from the csv as is, since they often have more fractional digits than quicken shows.
+pub struct CoinbaseReader;
+
+impl Reader for CoinbaseReader {
+    fn csv_header(&self) -> String {
+        r#"ID,Timestamp,Transaction Type,Asset,Quantity Transacted,Price Currency,Price at Transaction,Subtotal,Total (inclusive of fees and/or spread),Fees and/or Spread,Notes"#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d %H:%M:%S".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut qif_actions = <dyn Reader>::from_csv::<CoinbaseTransaction>(bufreader, securities)?;
+        // coinbase has listed both newest and oldest first over time.
+        qif_actions.sort_by_key(QifAction::date);
+        Ok(qif_actions)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseTransaction {
+    #[serde(rename = "Timestamp")]
+    pub timestamp: String,
+    #[serde(rename = "Transaction Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Asset")]
+    pub asset: String,
+    #[serde(rename = "Quantity Transacted")]
+    pub quantity: String,
+    #[serde(rename = "Price at Transaction")]
+    pub price: String,
+    #[serde(rename = "Subtotal")]
+    pub subtotal: String,
+    #[serde(rename = "Total (inclusive of fees and/or spread)")]
+    pub total: String,
+    #[serde(rename = "Fees and/or Spread")]
+    pub fees: String,
+    #[serde(rename = "Notes")]
+    pub notes: String,
+}
+
+// the absolute value of a quantity or amount, keeping all of its digits.
+fn unsigned(amount: &str) -> String {
+    clean_amount(amount).trim_start_matches('-').to_string()
+}
+
+impl CoinbaseTransaction {
+    // timestamps look like "2024-01-15 18:23:45 UTC" or "2024-01-15T18:23:45Z".
+    fn get_date(&self) -> Result<NaiveDate> {
+        let date = self.timestamp.get(..10).unwrap_or("");
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from coinbase: {}", &self.timestamp))
+    }
+
+    fn to_trade(&self, symbol: &str) -> Result<Trade> {
+        Ok(Trade {
+            date: self.get_date()?,
+            symbol: symbol.to_string(),
+            price: unsigned(&self.price),
+            quantity: unsigned(&self.quantity),
+            amount: unsigned(&self.total),
+            fees: unsigned(&self.fees),
+            memo: None,
+        })
+    }
+
+    // converts say "Converted 0.01 ETH to 0.00052 BTC", into a sell of one asset and a buy
+    // of the other.   The asset sold is valued at its spot price, the fee taken from that
+    // value leaves the amount the other asset is bought for.
+    fn to_convert(&self, symbols: &mut Symbols) -> Result<Vec<QifAction>> {
+        let notes_re = Regex::new(r"Converted [\d,\.]+ \S+ to ([\d,\.]+) (\S+)")?;
+        let notes_cap = notes_re
+            .captures(&self.notes)
+            .ok_or(eyre!("Could not find converted asset in : {}", &self.notes))?;
+        let to_quantity = clean_amount(&notes_cap[1]);
+        let to_symbol = notes_cap[2].to_string();
+        symbols.enter_if_not_found(&to_symbol, &to_symbol, &SecurityType::Crypto)?;
+
+        let quantity = parse_amount(&self.quantity)?.abs();
+        let fees = parse_amount(&self.fees)?.abs();
+        let amount = format_amount(quantity * parse_amount(&self.price)?.abs() - fees);
+        let sell = Trade {
+            amount: amount.clone(),
+            ..self.to_trade(&self.asset)?
+        };
+        let buy = Trade {
+            date: sell.date,
+            symbol: to_symbol,
+            price: format_quantity(parse_amount(&amount)? / parse_amount(&to_quantity)?),
+            quantity: to_quantity,
+            amount,
+            fees: "".to_string(),
+            memo: Some(self.notes.clone()),
+        };
+        Ok(vec![
+            QifAction::Sell { trade: sell },
+            QifAction::Buy { trade: buy },
+        ])
+    }
+
+    // rewards are entered as shares in, with their value when received as cost basis.
+    fn to_reward(&self) -> Result<QifAction> {
+        let quantity = unsigned(&self.quantity);
+        let price = unsigned(&self.price);
+        let amount = if self.subtotal.is_empty() {
+            format_amount(parse_amount(&quantity)? * parse_amount(&price)?)
+        } else {
+            unsigned(&self.subtotal)
+        };
+        Ok(QifAction::ShrsIn {
+            date: self.get_date()?,
+            symbol: self.asset.clone(),
+            quantity,
+            price: Some(price),
+            amount: Some(amount),
+        })
+    }
+}
+
+impl Transaction for CoinbaseTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let csv_type = self.transaction_type.as_str();
+        if !matches!(csv_type, "Deposit" | "Withdrawal") {
+            symbols.enter_if_not_found(&self.asset, &self.asset, &SecurityType::Crypto)?;
+        }
+        match csv_type {
+            "Buy" | "Advanced Trade Buy" => {
+                let trade = self.to_trade(&self.asset)?;
+                res.push(QifAction::Buy { trade });
+            }
+            "Sell" | "Advanced Trade Sell" => {
+                let trade = self.to_trade(&self.asset)?;
+                res.push(QifAction::Sell { trade });
+            }
+            "Convert" => {
+                res.append(&mut self.to_convert(symbols)?);
+            }
+            "Staking Income" | "Rewards Income" | "Learning Reward" | "Inflation Reward"
+            | "Coinbase Earn" => {
+                res.push(self.to_reward()?);
+            }
+            "Receive" => {
+                res.push(QifAction::ShrsIn {
+                    date: self.get_date()?,
+                    symbol: self.asset.clone(),
+                    quantity: unsigned(&self.quantity),
+                    price: None,
+                    amount: None,
+                });
+            }
+            "Send" => {
+                res.push(QifAction::ShrsOut {
+                    date: self.get_date()?,
+                    symbol: self.asset.clone(),
+                    quantity: unsigned(&self.quantity),
+                    price: None,
+                    amount: None,
+                });
+            }
+            "Deposit" | "Withdrawal" => {
+                res.push(QifAction::Generic {
+                    date: self.get_date()?,
+                    payee: self.notes.clone(),
+                    memo: Some(csv_type.to_string()),
+                    category: None,
+                    amount: clean_amount(&self.total),
+                });
+            }
+            _ => {
+                let message =
+                    "Unrecognized transaction type found in .CSV file : ".to_string() + csv_type;
+                return Err(eyre!(message));
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_coinbase() -> Result<()> {
+        let csv = CoinbaseReader {}.csv_header()
+            + "\n"
+            + "c,2024-01-17 10:00:00 UTC,Convert,ETH,-0.01,USD,$2500.00,$24.50,$25.00,$0.50,Converted 0.01 ETH to 0.00058 BTC\n"
+            + "b,2024-01-16 10:00:00 UTC,Staking Income,ETH,0.000123456789,USD,$2500.00,$0.31,$0.31,$0.00,\n"
+            + "a,2024-01-15 10:00:00 UTC,Buy,ETH,0.02,USD,$2500.00,$50.00,$51.00,$1.00,Bought 0.02 ETH for 51.00 USD\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = CoinbaseReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 4);
+
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.quantity, "0.02");
+                assert_eq!(trade.amount, "51.00");
+                assert_eq!(trade.fees, "1.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::ShrsIn {
+                quantity, amount, ..
+            } => {
+                assert_eq!(quantity, "0.000123456789");
+                assert_eq!(amount.as_deref(), Some("0.31"));
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match (&actions[2], &actions[3]) {
+            (QifAction::Sell { trade: sell }, QifAction::Buy { trade: buy }) => {
+                assert_eq!(sell.symbol, "ETH");
+                assert_eq!(sell.quantity, "0.01");
+                // 0.01 at $2500.00 less the $0.50 fee.
+                assert_eq!(sell.amount, "24.50");
+                assert_eq!(sell.fees, "0.50");
+                assert_eq!(buy.symbol, "BTC");
+                assert_eq!(buy.quantity, "0.00058");
+                assert_eq!(buy.amount, "24.50");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
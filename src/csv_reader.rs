@@ -1,4 +1,5 @@
 use stable_eyre::eyre::*;
+use std::collections::HashMap;
 use std::io::BufRead;
 
 use crate::opt::AccountType;
@@ -9,14 +10,48 @@ use crate::transactions_qif::*;
 pub trait Reader {
     fn csv_header(&self) -> String;
 
+    // Readers for input without a csv header line (json for example) return an empty
+    // csv_header and recognize their input from its contents instead.
+    fn recognizes(&self, _contents: &[u8]) -> bool {
+        false
+    }
+
+    // The format of the dates (and times if any) in the reader's csv, used to write the
+    // dates of a spreadsheet turned into csv for the reader.
+    fn date_format(&self) -> String {
+        "%m/%d/%Y".to_string()
+    }
+
     fn to_transactions(
         &self,
         bufreader: &mut dyn BufRead,
         securities: &mut Option<Symbols>,
     ) -> Result<Vec<QifAction>>;
+
+    // Readers for files holding several accounts (a multi-currency account for example)
+    // return the transactions of each account separately, each named by a suffix for its
+    // output file names.   Transactions under an empty name go to the account given on
+    // the command line.
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        Ok(vec![(
+            String::new(),
+            self.to_transactions(bufreader, securities)?,
+        )])
+    }
+
+    // The currency of an account of to_transactions_by_account, for readers whose accounts
+    // are balances in different currencies.   None for the currency given on the command
+    // line.
+    fn account_currency(&self, _account: &str) -> Option<String> {
+        None
+    }
 }
 
-impl dyn Reader {
+impl<'a> dyn Reader + 'a {
     // read transactions from qif, then convert to qif_actions.
     // Both of these processes are specific to the particular type of account.
     pub fn to_qif_transactions(
@@ -25,8 +60,23 @@ impl dyn Reader {
         account_type: AccountType,
         securities: &mut Option<Symbols>,
     ) -> Result<QifTransactions> {
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        let mut account_actions: Vec<(String, Vec<QifAction>)> = Vec::new();
+        let mut currencies: HashMap<String, String> = HashMap::new();
+        for (account, mut actions) in self.to_transactions_by_account(bufreader, securities)? {
+            if account.is_empty() {
+                qif_actions.append(&mut actions);
+            } else {
+                if let Some(currency) = self.account_currency(&account) {
+                    currencies.insert(account.clone(), currency);
+                }
+                account_actions.push((account, actions));
+            }
+        }
         Ok(QifTransactions {
-            qif_actions: self.to_transactions(bufreader, securities)?,
+            qif_actions,
+            account_actions,
+            currencies,
             account_type,
             symbols: securities.take(),
         })
@@ -44,7 +94,10 @@ impl dyn Reader {
         for<'de> T: serde::Deserialize<'de> + Transaction + 'static,
     {
         let mut qif_actions: Vec<Vec<QifAction>> = Vec::new();
-        let mut rdr = csv::Reader::from_reader(bufreader);
+        // flexible because some csv files (chase) end every row but the header with a comma.
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
         for record in rdr.deserialize::<T>() {
             if record.is_err() {
                 // some csv files are not too clean.
@@ -57,4 +110,42 @@ impl dyn Reader {
         // reversing because csv files typically have newest transactions first.
         Ok(qif_actions.into_iter().rev().flatten().collect())
     }
+
+    // like from_csv, for csv files with a column naming the account of each transaction.
+    // The transactions are split by account, with the accounts in name order.
+    pub fn from_csv_by_account<T>(
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>>
+    where
+        for<'de> T: serde::Deserialize<'de> + AccountTransaction + 'static,
+    {
+        let mut entries: Vec<(String, Vec<QifAction>)> = Vec::new();
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+        for record in rdr.deserialize::<T>() {
+            if record.is_err() {
+                // some csv files are not too clean.
+                break;
+            }
+            let record = record?;
+            entries.push((record.account(), record.to_qif_action(securities)?));
+        }
+        Ok(group_by_account(entries))
+    }
+}
+
+// the actions of each row, paired with their account, grouped by account in name order.
+pub fn group_by_account(entries: Vec<(String, Vec<QifAction>)>) -> Vec<(String, Vec<QifAction>)> {
+    // reversing because csv files typically have newest transactions first.
+    let mut accounts: Vec<(String, Vec<QifAction>)> = Vec::new();
+    for (name, mut qif_actions) in entries.into_iter().rev() {
+        match accounts.iter_mut().find(|(account, _)| *account == name) {
+            Some((_, actions)) => actions.append(&mut qif_actions),
+            None => accounts.push((name, qif_actions)),
+        }
+    }
+    accounts.sort_by(|a, b| a.0.cmp(&b.0));
+    accounts
 }
@@ -0,0 +1,122 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::clean_amount;
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Empower (formerly Personal Capital) transactions export, holding every linked account.
+// The transactions are split by account name, with one output .qif per account.
+pub struct EmpowerReader;
+
+impl Reader for EmpowerReader {
+    fn csv_header(&self) -> String {
+        r#"Date,Account,Description,Category,Tags,Amount"#.to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<EmpowerTransaction>(bufreader, securities)
+    }
+
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        <dyn Reader>::from_csv_by_account::<EmpowerTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmpowerTransaction {
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Account")]
+    pub account: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+    #[serde(rename = "Tags")]
+    pub tags: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+}
+
+impl EmpowerTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from empower: {}", &self.date))
+    }
+}
+
+impl Transaction for EmpowerTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        Ok(vec![QifAction::Generic {
+            date: self.get_date()?,
+            payee: self.description.clone(),
+            memo: if self.tags.is_empty() {
+                None
+            } else {
+                Some(self.tags.clone())
+            },
+            category: if self.category.is_empty() || self.category == "Uncategorized" {
+                None
+            } else {
+                Some(self.category.clone())
+            },
+            amount: clean_amount(&self.amount),
+        }])
+    }
+}
+
+impl AccountTransaction for EmpowerTransaction {
+    fn account(&self) -> String {
+        self.account.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_empower() -> Result<()> {
+        let csv = EmpowerReader {}.csv_header()
+            + "\n"
+            + "2024-01-16,Chase Sapphire - Ending in 1234,Grocer,Groceries,,-12.34\n"
+            + "2024-01-15,Ally Savings - Ending in 9876,Interest,Interest,Tax,\"1,000.00\"\n";
+        let accounts =
+            EmpowerReader {}.to_transactions_by_account(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].0, "Ally Savings - Ending in 9876");
+        match &accounts[0].1[0] {
+            QifAction::Generic {
+                memo,
+                category,
+                amount,
+                ..
+            } => {
+                assert_eq!(memo.as_deref(), Some("Tax"));
+                assert_eq!(category.as_deref(), Some("Interest"));
+                assert_eq!(amount, "1000.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,445 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::option_symbol::{occ_symbol, option_name};
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// E*TRADE brokerage transactions (DownloadTxnHistory.csv).
+// The file starts with a few lines describing the account before the header.
+pub struct EtradeReader;
+
+impl Reader for EtradeReader {
+    fn csv_header(&self) -> String {
+        r#"TransactionDate,TransactionType,SecurityType,Symbol,Quantity,Amount,Price,Commission,Description"#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%m/%d/%y".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<EtradeTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EtradeTransaction {
+    #[serde(rename = "TransactionDate")]
+    pub date: String,
+    #[serde(rename = "TransactionType")]
+    pub transaction_type: String,
+    #[serde(rename = "SecurityType")]
+    pub security_type: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Quantity")]
+    pub quantity: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Price")]
+    pub price: String,
+    #[serde(rename = "Commission")]
+    pub commission: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+}
+
+impl EtradeTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%y")
+            .with_context(|| format!("Could not parse date from E*TRADE: {}", &self.date))
+    }
+
+    // E*TRADE option symbols look like "AAPL Jan 19 '24 $150 Call"
+    fn get_option(&self) -> Result<(String, String)> {
+        let symbol_re = Regex::new(
+            r"(?x)^
+                               ([A-Z\.]+)                        # underlying symbol
+                               \ ([A-Z][a-z]{2}\ \d{1,2}\ '\d{2}) # expiration date
+                               \ \$([\d\.]+)                     # strike price
+                               \ (Call|Put)                      # put or call
+                              $",
+        )?;
+
+        let symbol_cap = symbol_re
+            .captures(self.symbol.trim())
+            .ok_or(eyre!("This is not an option!"))?;
+
+        let expiration = NaiveDate::parse_from_str(&symbol_cap[2], "%b %d '%y")?;
+        let put_or_call = if &symbol_cap[4] == "Call" { "C" } else { "P" };
+        let symbol = occ_symbol(&symbol_cap[1], &expiration, put_or_call, &symbol_cap[3])?;
+        let name = option_name(
+            put_or_call,
+            &symbol_cap[1],
+            &symbol_cap[1],
+            &expiration,
+            &symbol_cap[3],
+        );
+        Ok((symbol, name))
+    }
+
+    fn security_details(&self) -> Result<(String, String, SecurityType)> {
+        match self.get_option() {
+            Ok((symbol, name)) => Ok((symbol, name, SecurityType::Option)),
+            Err(_) => {
+                let security_type = if self.security_type == "MF" {
+                    SecurityType::MutualFund
+                } else {
+                    SecurityType::Stock
+                };
+                Ok((
+                    self.symbol.trim().to_string(),
+                    self.description.trim().to_string(),
+                    security_type,
+                ))
+            }
+        }
+    }
+
+    fn multiplier(security_type: &SecurityType) -> f64 {
+        if *security_type == SecurityType::Option {
+            100.0
+        } else {
+            1.0
+        }
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let (symbol, name, security_type) = self.security_details()?;
+
+        // E*TRADE signs quantity and amount by direction but quicken wants them positive.
+        let quantity = parse_amount(&self.quantity)?.abs() * Self::multiplier(&security_type);
+        let date: NaiveDate = self.get_date()?;
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let res = Trade {
+            date,
+            symbol,
+            price: self.price.trim().to_string(),
+            quantity: format_quantity(quantity),
+            amount: self.amount.trim().trim_start_matches('-').to_string(),
+            fees: self.commission.trim().to_string(),
+            memo: None,
+        };
+        Ok(res)
+    }
+
+    // A positive quantity closes a short position, a negative quantity closes a long one.
+    fn to_closed_option(&self, symbols: &mut Symbols) -> Result<QifAction> {
+        let (symbol, name, security_type) = self.security_details()?;
+        if security_type != SecurityType::Option {
+            return Err(eyre!(
+                "Option expired or assigned found in CSV for non-option: {}",
+                self.symbol
+            ));
+        }
+
+        let quantity = parse_amount(&self.quantity)?;
+        let date: NaiveDate = self.get_date()?;
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let trade = Trade {
+            date,
+            symbol,
+            price: "".to_string(),
+            quantity: format_quantity(quantity.abs() * Self::multiplier(&security_type)),
+            amount: "".to_string(),
+            fees: "".to_string(),
+            memo: None,
+        };
+        if quantity > 0.0 {
+            Ok(QifAction::CvrShrt { trade })
+        } else {
+            Ok(QifAction::Sell { trade })
+        }
+    }
+}
+
+impl Transaction for EtradeTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let csv_type = self.transaction_type.as_str();
+        match csv_type {
+            "Bought" | "Bought To Open" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Buy { trade });
+            }
+            "Sold" | "Sold To Close" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Sell { trade });
+            }
+            "Sold Short" | "Sold To Open" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::ShtSell { trade });
+            }
+            "Bought To Cover" | "Bought To Close" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::CvrShrt { trade });
+            }
+            "Option Expired" | "Option Assigned" | "Option Exercised" => {
+                res.push(self.to_closed_option(symbols)?);
+            }
+            "Dividend" | "Qualified Dividend" => {
+                let (symbol, name, security_type) = self.security_details()?;
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::Div {
+                    date: self.get_date()?,
+                    symbol,
+                    amount: self.amount.trim().to_string(),
+                });
+            }
+            "Margin Interest" => {
+                // margin interest is negative but quicken wants it positive.
+                res.push(QifAction::MargInt {
+                    date: self.get_date()?,
+                    memo: self.description.clone(),
+                    amount: self.amount.trim().trim_start_matches('-').to_string(),
+                });
+            }
+            "Receive" => {
+                // shares arriving from outside, for example from a stock plan account.
+                let (symbol, name, security_type) = self.security_details()?;
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::ShrsIn {
+                    date: self.get_date()?,
+                    symbol,
+                    quantity: format_quantity(parse_amount(&self.quantity)?.abs()),
+                    price: None,
+                    amount: None,
+                });
+            }
+            _ => {
+                if parse_amount(&self.quantity)? == 0.0 {
+                    res.push(QifAction::Generic {
+                        date: self.get_date()?,
+                        payee: self.description.clone(),
+                        memo: Some(self.transaction_type.clone()),
+                        category: None,
+                        amount: self.amount.trim().to_string(),
+                    });
+                } else {
+                    let message = "Unrecognized transaction type found in .CSV file : ".to_string()
+                        + csv_type;
+                    return Err(eyre!(message));
+                }
+            }
+        };
+        Ok(res)
+    }
+}
+
+// E*TRADE / Morgan Stanley at Work stock plan "Benefit History", saved as csv.
+// Only the "Event" records are used:  RSU releases, shares withheld for taxes and ESPP
+// purchases.   Est. Market Value is the fair market value per share on the event date.
+pub struct EtradeBenefitHistoryReader;
+
+impl Reader for EtradeBenefitHistoryReader {
+    fn csv_header(&self) -> String {
+        r#"Record Type,Symbol,Plan Type,Grant Number,Date,Event Type,Qty. or Amount,Est. Market Value,Purchase Price"#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut qif_actions = <dyn Reader>::from_csv::<EtradeBenefitEvent>(bufreader, securities)?;
+        // the benefit history is oldest first, unlike the csv files from_csv expects.
+        // Shares withheld for tax come from the shares released that day, so follow them.
+        qif_actions
+            .sort_by_key(|action| (action.date(), matches!(action, QifAction::ShrsOut { .. })));
+        Ok(qif_actions)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EtradeBenefitEvent {
+    #[serde(rename = "Record Type")]
+    pub record_type: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Grant Number")]
+    pub grant_number: String,
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Event Type")]
+    pub event_type: String,
+    #[serde(rename = "Qty. or Amount")]
+    pub quantity: String,
+    #[serde(rename = "Est. Market Value")]
+    pub market_value: String,
+    #[serde(rename = "Purchase Price")]
+    pub purchase_price: String,
+}
+
+impl EtradeBenefitEvent {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from E*TRADE: {}", &self.date))
+    }
+
+    // returns quantity, price per share and total cost basis.
+    fn cost_basis(&self, price: &str) -> Result<(String, String, String)> {
+        let quantity = parse_amount(&self.quantity)?.abs();
+        let price = parse_amount(price)?;
+        Ok((
+            format_quantity(quantity),
+            format_quantity(price),
+            format_amount(quantity * price),
+        ))
+    }
+}
+
+impl Transaction for EtradeBenefitEvent {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        if self.record_type != "Event" {
+            return Ok(res);
+        }
+
+        let symbol = self.symbol.trim().to_string();
+        symbols.enter_if_not_found(&symbol, &symbol, &SecurityType::Stock)?;
+
+        let csv_event = self.event_type.as_str();
+        match csv_event {
+            "Shares released" => {
+                // RSU shares arrive with a cost basis of their market value at release.
+                let (quantity, price, amount) = self.cost_basis(&self.market_value)?;
+                res.push(QifAction::ShrsIn {
+                    date: self.get_date()?,
+                    symbol,
+                    quantity,
+                    price: Some(price),
+                    amount: Some(amount),
+                });
+            }
+            "Shares withheld for tax" | "Tax Withholding" => {
+                // part of the released shares never arrive because they pay the taxes.
+                let (quantity, price, amount) = self.cost_basis(&self.market_value)?;
+                res.push(QifAction::ShrsOut {
+                    date: self.get_date()?,
+                    symbol,
+                    quantity,
+                    price: Some(price),
+                    amount: Some(amount),
+                });
+            }
+            "Purchase" => {
+                // ESPP shares are paid for through payroll, not from the brokerage cash,
+                // so they arrive with a cost basis of the purchase price.
+                let (quantity, price, amount) = self.cost_basis(&self.purchase_price)?;
+                res.push(QifAction::ShrsIn {
+                    date: self.get_date()?,
+                    symbol,
+                    quantity,
+                    price: Some(price),
+                    amount: Some(amount),
+                });
+            }
+            _ => {
+                println!(
+                    "Stock plan event not handled : \"{}\" for grant {}.",
+                    csv_event, self.grant_number
+                );
+                println!("{:#?}", self);
+                println!();
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_benefit_history() -> Result<()> {
+        let csv = EtradeBenefitHistoryReader {}.csv_header()
+            + "\n"
+            + "Grant,XYZ,RS,G1,,,,,\n"
+            + "Event,XYZ,RS,G1,03/15/2024,Shares released,100,$50.00,\n"
+            + "Event,XYZ,RS,G1,03/15/2024,Shares withheld for tax,38,$50.00,\n"
+            + "Event,XYZ,ESPP,E1,06/30/2024,Purchase,20,$60.00,$51.00\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions =
+            EtradeBenefitHistoryReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 3);
+
+        // oldest first, shares released before the shares withheld from them.
+        match &actions[0] {
+            QifAction::ShrsIn { amount, .. } => {
+                assert_eq!(amount.as_deref(), Some("5000.00"));
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::ShrsOut { quantity, .. } => assert_eq!(quantity, "38"),
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[2] {
+            QifAction::ShrsIn {
+                quantity,
+                price,
+                amount,
+                ..
+            } => {
+                assert_eq!(quantity, "20");
+                assert_eq!(price.as_deref(), Some("51"));
+                assert_eq!(amount.as_deref(), Some("1020.00"));
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_etrade() -> Result<()> {
+        let csv = EtradeReader {}.csv_header()
+            + "\n"
+            + "01/22/24,Dividend,EQ,AAPL,0,2.40,0,0,APPLE INC\n"
+            + "01/19/24,Option Expired,OPTN,AAPL Jan 19 '24 $150 Call,1,0,0,0,CALL AAPL 01/19/24 150\n"
+            + "01/16/24,Sold To Open,OPTN,AAPL Jan 19 '24 $150 Call,-1,249.35,2.5,0.65,CALL AAPL 01/19/24 150\n"
+            + "01/15/24,Bought,EQ,AAPL,10,-1855.00,185.5,0,APPLE INC\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = EtradeReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        match &actions[..] {
+            [QifAction::Buy { trade: buy }, QifAction::ShtSell { trade: sell }, QifAction::CvrShrt { trade: expired }, QifAction::Div { amount, .. }] =>
+            {
+                assert_eq!(buy.quantity, "10");
+                assert_eq!(buy.amount, "1855.00");
+                assert_eq!(sell.symbol, "AAPL  240119C00150000");
+                assert_eq!(sell.quantity, "100");
+                assert_eq!(sell.fees, "0.65");
+                assert_eq!(expired.symbol, sell.symbol);
+                assert_eq!(expired.quantity, "100");
+                assert_eq!(amount, "2.40");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, parse_amount};
+use crate::csv_reader::*;
+use crate::option_symbol::{occ_symbol, option_name};
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+pub struct FidelityReader;
+
+impl Reader for FidelityReader {
+    fn csv_header(&self) -> String {
+        r#"Run Date,Action,Symbol,Security Description,Security Type,Quantity,Price ($),Commission ($),Fees ($),Accrued Interest ($),Amount ($),Settlement Date"#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<FidelityTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FidelityTransaction {
+    #[serde(rename = "Run Date")]
+    pub date: String,
+    #[serde(rename = "Action")]
+    pub action: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Security Description")]
+    pub description: String,
+    #[serde(rename = "Quantity")]
+    pub quantity: String,
+    #[serde(rename = "Price ($)")]
+    pub price: String,
+    #[serde(rename = "Commission ($)")]
+    pub commission: String,
+    #[serde(rename = "Fees ($)")]
+    pub fees: String,
+    #[serde(rename = "Amount ($)")]
+    pub amount: String,
+}
+
+impl FidelityTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(self.date.trim(), "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from fidelity: {}", &self.date))
+    }
+
+    // fidelity reports commission and fees separately but quicken has only one field for both.
+    fn get_fees(&self) -> Result<String> {
+        let commission = self.commission.trim();
+        let fees = self.fees.trim();
+        if commission.is_empty() && fees.is_empty() {
+            return Ok(String::new());
+        }
+        Ok(format_amount(
+            parse_amount(commission)? + parse_amount(fees)?,
+        ))
+    }
+
+    fn is_zero_or_empty(field: &str) -> bool {
+        let trimmed = field.trim();
+        trimmed.is_empty() || trimmed.parse::<f64>().is_ok_and(|v| v == 0.0)
+    }
+
+    // Fidelity option symbols look like " -AAPL240119C150" or " -AAPL240119P152.5"
+    fn get_option(&self) -> Result<(String, String)> {
+        let symbol_re = Regex::new(
+            r"(?x)^
+                               -([A-Z]+)                # underlying symbol
+                               (\d{6})                  # expiration date as yymmdd
+                               ([PC])                   # put or call
+                               ([\d\.]+)                # strike price
+                              $",
+        )?;
+
+        let symbol_cap = symbol_re
+            .captures(self.symbol.trim())
+            .ok_or(eyre!("This is not an option!"))?;
+
+        let expiration = NaiveDate::parse_from_str(&symbol_cap[2], "%y%m%d")?;
+        let symbol = occ_symbol(&symbol_cap[1], &expiration, &symbol_cap[3], &symbol_cap[4])?;
+
+        // description looks like "CALL (AAPL) APPLE INC JAN 19 24 $150 (100 SHS)"
+        let description_re = Regex::new(
+            r"(?x)^
+                               (?:PUT|CALL)                  # PUT or CALL
+                               \ \([A-Z]+\)                  # (underlying symbol)
+                               \ (.*?)                       # description of underlying
+                               \ [A-Z]{3}\ \d{2}\ \d{2}      # expiration date
+                               \ \$.*                        # strike price and the rest
+                               $",
+        )?;
+        let underlying_description = match description_re.captures(self.description.trim()) {
+            Some(description_cap) => description_cap[1].to_string(),
+            None => self.description.trim().to_string(),
+        };
+
+        let name = option_name(
+            &symbol_cap[3],
+            &underlying_description,
+            &symbol_cap[1],
+            &expiration,
+            &symbol_cap[4],
+        );
+
+        Ok((symbol, name))
+    }
+
+    fn security_details(&self) -> Result<(String, String, SecurityType)> {
+        match self.get_option() {
+            Ok((symbol, name)) => Ok((symbol, name, SecurityType::Option)),
+            Err(_) => {
+                let name = self.description.trim().to_string();
+                let symbol = self.symbol.trim().to_string();
+                Ok((symbol, name, SecurityType::Stock))
+            }
+        }
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let (symbol, name, security_type) = self.security_details()?;
+
+        // fidelity signs quantity and amount by direction but quicken wants them positive.
+        let quantity = self.quantity.trim().trim_start_matches('-').to_string()
+            + if security_type == SecurityType::Option {
+                "00"
+            } else {
+                ""
+            };
+        let amount = self.amount.trim().trim_start_matches('-').to_string();
+        let date: NaiveDate = self.get_date()?;
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let res = Trade {
+            date,
+            symbol,
+            price: self.price.trim().to_string(),
+            quantity,
+            amount,
+            fees: self.get_fees()?,
+            memo: None,
+        };
+        Ok(res)
+    }
+
+    // Assigned and expired options leave the account with no money changing hands.
+    // A positive quantity closes a short position, a negative quantity closes a long one.
+    fn to_closed_option(&self, symbols: &mut Symbols) -> Result<QifAction> {
+        let (symbol, name, security_type) = self.security_details()?;
+        if security_type != SecurityType::Option {
+            return Err(eyre!(
+                "Assigned or expired found in CSV for non-option: {}",
+                self.symbol
+            ));
+        }
+
+        let quantity = self.quantity.trim();
+        let is_short = !quantity.starts_with('-');
+        let date: NaiveDate = self.get_date()?;
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let trade = Trade {
+            date,
+            symbol,
+            price: "".to_string(),
+            quantity: quantity.trim_start_matches('-').to_string() + "00",
+            amount: "".to_string(),
+            fees: "".to_string(),
+            memo: None,
+        };
+        if is_short {
+            Ok(QifAction::CvrShrt { trade })
+        } else {
+            Ok(QifAction::Sell { trade })
+        }
+    }
+}
+
+impl Transaction for FidelityTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        // fidelity actions carry the security description and more, for example:
+        //   "YOU BOUGHT APPLE INC (AAPL) (Cash)"
+        let csv_action = self.action.trim();
+        if csv_action.starts_with("YOU SOLD OPENING TRANSACTION") {
+            let trade = self.to_trade(symbols)?;
+            res.push(QifAction::ShtSell { trade });
+        } else if csv_action.starts_with("YOU BOUGHT CLOSING TRANSACTION") {
+            let trade = self.to_trade(symbols)?;
+            res.push(QifAction::CvrShrt { trade });
+        } else if csv_action.starts_with("YOU BOUGHT") || csv_action.starts_with("REINVESTMENT") {
+            let trade = self.to_trade(symbols)?;
+            res.push(QifAction::Buy { trade });
+        } else if csv_action.starts_with("YOU SOLD") {
+            let trade = self.to_trade(symbols)?;
+            res.push(QifAction::Sell { trade });
+        } else if csv_action.starts_with("ASSIGNED") || csv_action.starts_with("EXPIRED") {
+            res.push(self.to_closed_option(symbols)?);
+        } else if csv_action.starts_with("DIVIDEND RECEIVED") {
+            let (symbol, name, security_type) = self.security_details()?;
+            symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+            res.push(QifAction::Div {
+                date: self.get_date()?,
+                symbol,
+                amount: self.amount.trim().to_string(),
+            });
+        } else if csv_action.starts_with("SHORT-TERM CAP GAIN") {
+            let (symbol, name, security_type) = self.security_details()?;
+            symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+            res.push(QifAction::CGShort {
+                date: self.get_date()?,
+                symbol,
+                amount: self.amount.trim().to_string(),
+            });
+        } else if csv_action.starts_with("LONG-TERM CAP GAIN") {
+            let (symbol, name, security_type) = self.security_details()?;
+            symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+            res.push(QifAction::CGLong {
+                date: self.get_date()?,
+                symbol,
+                amount: self.amount.trim().to_string(),
+            });
+        } else if csv_action.starts_with("MARGIN INTEREST") {
+            // Margin Interest from fidelity is negative but quicken wants it positive.
+            res.push(QifAction::MargInt {
+                date: self.get_date()?,
+                memo: csv_action.to_string(),
+                amount: self.amount.trim().trim_start_matches('-').to_string(),
+            });
+        } else if Self::is_zero_or_empty(&self.quantity) && Self::is_zero_or_empty(&self.price) {
+            // interest, transfers, foreign tax and the like.
+            res.push(QifAction::Generic {
+                date: self.get_date()?,
+                payee: csv_action.to_string(),
+                memo: Some(self.description.trim().to_string()),
+                category: None,
+                amount: self.amount.trim().to_string(),
+            });
+        } else {
+            let message = "Unrecognized action found in .CSV file : ".to_string() + csv_action;
+            return Err(eyre!(message));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn transaction(
+        action: &str,
+        symbol: &str,
+        description: &str,
+        quantity: &str,
+    ) -> FidelityTransaction {
+        FidelityTransaction {
+            date: "01/19/2024".to_string(),
+            action: action.to_string(),
+            symbol: symbol.to_string(),
+            description: description.to_string(),
+            quantity: quantity.to_string(),
+            price: "".to_string(),
+            commission: "".to_string(),
+            fees: "".to_string(),
+            amount: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_get_option() -> Result<()> {
+        let t = transaction(
+            "EXPIRED CALL (AAPL) APPLE INC JAN 19 24 $150 (100 SHS) (Cash)",
+            " -AAPL240119C150",
+            "CALL (AAPL) APPLE INC JAN 19 24 $150 (100 SHS)",
+            "1",
+        );
+        let (symbol, name) = t.get_option()?;
+        assert_eq!(symbol, "AAPL  240119C00150000");
+        assert_eq!(name, "CALL : APPLE INC - AAPL 01/19/2024 150 C");
+
+        let stock = transaction("YOU BOUGHT", "AAPL", "APPLE INC", "10");
+        assert!(stock.get_option().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_fees() -> Result<()> {
+        let mut t = transaction("YOU BOUGHT", "AAPL", "APPLE INC", "10");
+        assert_eq!(t.get_fees()?, "");
+        t.commission = "4.95".to_string();
+        assert_eq!(t.get_fees()?, "4.95");
+        t.fees = "0.05".to_string();
+        assert_eq!(t.get_fees()?, "5.00");
+        t.fees = "n/a".to_string();
+        assert!(t.get_fees().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_short_option() -> Result<()> {
+        let t = transaction(
+            "EXPIRED CALL (AAPL) APPLE INC JAN 19 24 $150 (100 SHS) (Cash)",
+            " -AAPL240119C150",
+            "CALL (AAPL) APPLE INC JAN 19 24 $150 (100 SHS)",
+            "1",
+        );
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = t.to_qif_action(&mut symbols)?;
+        assert_eq!(actions.len(), 1);
+        match &actions[0] {
+            QifAction::CvrShrt { trade } => {
+                assert_eq!(trade.symbol, "AAPL  240119C00150000");
+                assert_eq!(trade.quantity, "100");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
 use crate::opt::AccountType;
 use crate::opt::Opt;
+use crate::opt::OutputFormat;
 use stable_eyre::eyre::*;
 use std::ffi::OsString;
 use std::path::PathBuf;
 
+#[derive(Clone)]
 pub struct FileNames {
     pub transactions_qif: PathBuf,
     pub linked_cash_qif: PathBuf,
@@ -19,11 +21,18 @@ impl FileNames {
             .file_name()
             .with_context(|| format!("Unable to get filename from : {:#?}", &opts.transactions))?;
 
-        let qif_transactions_base = PathBuf::from(transactions_file_name).with_extension("qif");
+        let extension = match &opts.output_format {
+            OutputFormat::Qif => "qif",
+            OutputFormat::Qfx => "qfx",
+            OutputFormat::Ledger => "journal",
+        };
+        let qif_transactions_base = PathBuf::from(transactions_file_name).with_extension(extension);
 
         let transactions_suffix = match &opts.account_type {
             AccountType::Cash => "cash_",
             AccountType::Invest => "invest_",
+            AccountType::CreditCard => "ccard_",
+            AccountType::Retirement => "retire_",
         };
         let mut t = OsString::from(transactions_suffix);
         t.push(&qif_transactions_base);
@@ -33,8 +42,9 @@ impl FileNames {
         t.push(&qif_transactions_base);
         let linked_cash_qif = PathBuf::from(&t);
 
+        // securities are always written as qif, qfx has them with the transactions.
         let mut t = OsString::from("securities_");
-        t.push(&qif_transactions_base);
+        t.push(PathBuf::from(transactions_file_name).with_extension("qif"));
         let securities_qif = PathBuf::from(&t);
 
         let filenames = FileNames {
@@ -45,6 +55,32 @@ impl FileNames {
         };
         Ok(filenames)
     }
+
+    // file names for one of several accounts found in the transactions file, such as the
+    // currency balances of a multi-currency account : "cash_statement.qif" becomes
+    // "cash_statement_EUR.qif".   The extension of the output format is kept.
+    pub fn for_account(&self, account: &str) -> FileNames {
+        let suffix: String = account
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let with_suffix = |path: &PathBuf| {
+            let mut t = path.file_stem().unwrap_or_default().to_os_string();
+            t.push("_");
+            t.push(&suffix);
+            if let Some(extension) = path.extension() {
+                t.push(".");
+                t.push(extension);
+            }
+            path.with_file_name(t)
+        };
+        FileNames {
+            transactions_qif: with_suffix(&self.transactions_qif),
+            linked_cash_qif: with_suffix(&self.linked_cash_qif),
+            securities_qif: self.securities_qif.clone(),
+            workdir: self.workdir.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -89,4 +125,37 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_for_account() {
+        let file_names = FileNames {
+            transactions_qif: PathBuf::from("cash_statement.qif"),
+            linked_cash_qif: PathBuf::from("linked_cash_statement.qif"),
+            securities_qif: PathBuf::from("securities_statement.qif"),
+            workdir: PathBuf::from("."),
+        };
+        let eur = file_names.for_account("EUR");
+        assert_eq!(
+            eur.transactions_qif,
+            PathBuf::from("cash_statement_EUR.qif")
+        );
+        assert_eq!(
+            eur.linked_cash_qif,
+            PathBuf::from("linked_cash_statement_EUR.qif")
+        );
+        assert_eq!(eur.securities_qif, file_names.securities_qif);
+        let qfx = FileNames {
+            transactions_qif: PathBuf::from("invest_statement.qfx"),
+            ..file_names.clone()
+        };
+        assert_eq!(
+            qfx.for_account("X1").transactions_qif,
+            PathBuf::from("invest_statement_X1.qfx")
+        );
+        let checking = file_names.for_account("Chase Checking");
+        assert_eq!(
+            checking.transactions_qif,
+            PathBuf::from("cash_statement_Chase_Checking.qif")
+        );
+    }
 }
@@ -15,8 +15,13 @@ pub fn find_matching_line<V: Copy, T: Seek + BufRead>(
                 if line.ends_with('\n') {
                     line.pop();
                 }
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+                // some exports (ynab for example) start with a byte order mark.
+                let trimmed = line.trim_start_matches('\u{feff}');
                 for (key, value) in collection {
-                    if line == *key {
+                    if trimmed == *key {
                         file.seek(SeekFrom::Current(-(num_bytes as i64)))?;
                         return Ok(Some(*value));
                     }
@@ -62,4 +67,28 @@ mod tests {
         assert_eq!(find_matching_line(&mut input, &collection2)?, None);
         Ok(())
     }
+
+    #[test]
+    fn test_find_matching_line_byte_order_mark() -> Result<()> {
+        let mut input = Cursor::new("\u{feff}header\nrow\n");
+
+        let collection = HashMap::from([("header".to_string(), 0)]);
+        assert_eq!(find_matching_line(&mut input, &collection)?, Some(0));
+        assert_eq!(input.position(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_matching_line_second_section() -> Result<()> {
+        // files such as vanguard's start with a section that has its own header.
+        let mut input = Cursor::new("holdings\r\nx,y\r\n\r\ntransactions\r\nz\r\n");
+
+        let collection = HashMap::from([("transactions".to_string(), 0)]);
+        assert_eq!(find_matching_line(&mut input, &collection)?, Some(0));
+
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+        assert_eq!(line, "transactions\r\n");
+        Ok(())
+    }
 }
@@ -0,0 +1,446 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::option_symbol::{occ_symbol, option_name};
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Interactive Brokers Flex Query exports.   Flex queries let the user pick the fields,
+// so the queries have to be set up with exactly the fields (and order) in these headers,
+// with dates in the default yyyyMMdd format.
+//
+// All amounts are converted to the base currency of the account using FXRateToBase.
+// Transactions in another currency note the original currency in their memo.
+
+pub struct IbkrTradesReader;
+
+impl Reader for IbkrTradesReader {
+    fn csv_header(&self) -> String {
+        r#""ClientAccountID","CurrencyPrimary","FXRateToBase","AssetClass","Symbol","Description","UnderlyingSymbol","Multiplier","Strike","Expiry","Put/Call","TradeDate","Quantity","TradePrice","IBCommission","NetCash","Buy/Sell","Open/CloseIndicator""#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y%m%d".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<IbkrTrade>(bufreader, securities)
+    }
+}
+
+pub struct IbkrCashReader;
+
+impl Reader for IbkrCashReader {
+    fn csv_header(&self) -> String {
+        r#""ClientAccountID","CurrencyPrimary","FXRateToBase","AssetClass","Symbol","Description","UnderlyingSymbol","Multiplier","Strike","Expiry","Put/Call","SettleDate","Amount","Type""#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y%m%d".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<IbkrCashTransaction>(bufreader, securities)
+    }
+}
+
+// the security columns shared by trades and cash transactions.
+// (serde flatten does not work with the csv crate, so the columns are repeated in each.)
+#[derive(Debug)]
+pub struct IbkrSecurity<'a> {
+    pub asset_class: &'a str,
+    pub symbol: &'a str,
+    pub description: &'a str,
+    pub underlying: &'a str,
+    pub multiplier: &'a str,
+    pub strike: &'a str,
+    pub expiry: &'a str,
+    pub put_call: &'a str,
+}
+
+impl IbkrSecurity<'_> {
+    fn security_details(&self) -> Result<(String, String, SecurityType)> {
+        match self.asset_class {
+            "OPT" | "FOP" => {
+                let expiration = get_date(self.expiry)?;
+                let symbol = occ_symbol(self.underlying, &expiration, self.put_call, self.strike)?;
+                let name = option_name(
+                    self.put_call,
+                    self.underlying,
+                    self.underlying,
+                    &expiration,
+                    self.strike,
+                );
+                Ok((symbol, name, SecurityType::Option))
+            }
+            "FUT" => Ok((
+                self.symbol.to_string(),
+                self.description.to_string(),
+                SecurityType::Future,
+            )),
+            "STK" => Ok((
+                self.symbol.to_string(),
+                self.description.to_string(),
+                SecurityType::Stock,
+            )),
+            _ => Err(eyre!(
+                "Unsupported asset class found in .CSV file : {}",
+                self.asset_class
+            )),
+        }
+    }
+
+    // quicken wants the number of underlying units for options and futures, not contracts.
+    fn multiplier(&self) -> Result<f64> {
+        if self.multiplier.trim().is_empty() {
+            return Ok(1.0);
+        }
+        parse_amount(self.multiplier)
+    }
+}
+
+fn get_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y%m%d")
+        .with_context(|| format!("Could not parse date from interactive brokers: {}", date))
+}
+
+fn get_fx_rate(fx_rate: &str) -> Result<f64> {
+    if fx_rate.trim().is_empty() {
+        return Ok(1.0);
+    }
+    parse_amount(fx_rate)
+}
+
+fn currency_memo(currency: &str, fx_rate: f64) -> Option<String> {
+    if fx_rate == 1.0 {
+        None
+    } else {
+        Some(format!("converted from {} at {}", currency, fx_rate))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IbkrTrade {
+    #[serde(rename = "CurrencyPrimary")]
+    pub currency: String,
+    #[serde(rename = "FXRateToBase")]
+    pub fx_rate: String,
+    #[serde(rename = "AssetClass")]
+    pub asset_class: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "UnderlyingSymbol")]
+    pub underlying: String,
+    #[serde(rename = "Multiplier")]
+    pub multiplier: String,
+    #[serde(rename = "Strike")]
+    pub strike: String,
+    #[serde(rename = "Expiry")]
+    pub expiry: String,
+    #[serde(rename = "Put/Call")]
+    pub put_call: String,
+    #[serde(rename = "TradeDate")]
+    pub date: String,
+    #[serde(rename = "Quantity")]
+    pub quantity: String,
+    #[serde(rename = "TradePrice")]
+    pub price: String,
+    #[serde(rename = "IBCommission")]
+    pub commission: String,
+    #[serde(rename = "NetCash")]
+    pub net_cash: String,
+    #[serde(rename = "Buy/Sell")]
+    pub buy_sell: String,
+    #[serde(rename = "Open/CloseIndicator")]
+    pub open_close: String,
+}
+
+impl IbkrTrade {
+    fn security(&self) -> IbkrSecurity<'_> {
+        IbkrSecurity {
+            asset_class: &self.asset_class,
+            symbol: &self.symbol,
+            description: &self.description,
+            underlying: &self.underlying,
+            multiplier: &self.multiplier,
+            strike: &self.strike,
+            expiry: &self.expiry,
+            put_call: &self.put_call,
+        }
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let (symbol, name, security_type) = self.security().security_details()?;
+        let fx_rate = get_fx_rate(&self.fx_rate)?;
+
+        let quantity = parse_amount(&self.quantity)?.abs() * self.security().multiplier()?;
+        let price = parse_amount(&self.price)? * fx_rate;
+        let amount = parse_amount(&self.net_cash)?.abs() * fx_rate;
+        let fees = parse_amount(&self.commission)?.abs() * fx_rate;
+
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let res = Trade {
+            date: get_date(&self.date)?,
+            symbol,
+            price: format_quantity(price),
+            quantity: format_quantity(quantity),
+            amount: format_amount(amount),
+            fees: format_amount(fees),
+            memo: currency_memo(&self.currency, fx_rate),
+        };
+        Ok(res)
+    }
+}
+
+impl Transaction for IbkrTrade {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        if self.asset_class == "CASH" {
+            println!("Currency conversion trades are not handled:");
+            println!("{:#?}", self);
+            println!();
+            return Ok(res);
+        }
+
+        // open/close indicator is "C;O" when a trade closes a position and opens the opposite
+        // one, for example selling 10 while long 5.   The row does not tell how much of the
+        // quantity closes, so the whole trade is entered as closing, to be split by hand.
+        let opening = self.open_close == "O";
+        if self.open_close.contains(';') {
+            println!(
+                "Trade both closing and opening a position found, entered as closing only. \
+                 Split it into the closing and the opening quantity in quicken:"
+            );
+            println!("{:#?}", self);
+            println!();
+        }
+        let trade = self.to_trade(symbols)?;
+        match (self.buy_sell.as_str(), opening) {
+            ("BUY", true) => res.push(QifAction::Buy { trade }),
+            ("BUY", false) => res.push(QifAction::CvrShrt { trade }),
+            ("SELL", true) => res.push(QifAction::ShtSell { trade }),
+            ("SELL", false) => res.push(QifAction::Sell { trade }),
+            _ => {
+                let message = "Unrecognized Buy/Sell found in .CSV file : ".to_string()
+                    + self.buy_sell.as_str();
+                return Err(eyre!(message));
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IbkrCashTransaction {
+    #[serde(rename = "CurrencyPrimary")]
+    pub currency: String,
+    #[serde(rename = "FXRateToBase")]
+    pub fx_rate: String,
+    #[serde(rename = "AssetClass")]
+    pub asset_class: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "UnderlyingSymbol")]
+    pub underlying: String,
+    #[serde(rename = "Multiplier")]
+    pub multiplier: String,
+    #[serde(rename = "Strike")]
+    pub strike: String,
+    #[serde(rename = "Expiry")]
+    pub expiry: String,
+    #[serde(rename = "Put/Call")]
+    pub put_call: String,
+    #[serde(rename = "SettleDate")]
+    pub date: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Type")]
+    pub transaction_type: String,
+}
+
+impl IbkrCashTransaction {
+    fn security(&self) -> IbkrSecurity<'_> {
+        IbkrSecurity {
+            asset_class: &self.asset_class,
+            symbol: &self.symbol,
+            description: &self.description,
+            underlying: &self.underlying,
+            multiplier: &self.multiplier,
+            strike: &self.strike,
+            expiry: &self.expiry,
+            put_call: &self.put_call,
+        }
+    }
+}
+
+impl Transaction for IbkrCashTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let fx_rate = get_fx_rate(&self.fx_rate)?;
+        let amount = parse_amount(&self.amount)? * fx_rate;
+        let date = get_date(&self.date)?;
+        let memo = match currency_memo(&self.currency, fx_rate) {
+            Some(currency_memo) => format!("{} ({})", self.description, currency_memo),
+            None => self.description.clone(),
+        };
+
+        let csv_type = self.transaction_type.as_str();
+        match csv_type {
+            "Dividends" | "Payment In Lieu Of Dividends" => {
+                let (symbol, name, security_type) = self.security().security_details()?;
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::Div {
+                    date,
+                    symbol,
+                    amount: format_amount(amount),
+                });
+            }
+            "Broker Interest Paid" => {
+                // interest paid is negative but quicken wants it positive.
+                res.push(QifAction::MargInt {
+                    date,
+                    memo,
+                    amount: format_amount(amount.abs()),
+                });
+            }
+            "Withholding Tax"
+            | "Broker Interest Received"
+            | "Bond Interest Received"
+            | "Deposits/Withdrawals"
+            | "Other Fees"
+            | "Commission Adjustments" => {
+                res.push(QifAction::Generic {
+                    date,
+                    payee: self.description.clone(),
+                    memo: Some(memo),
+                    category: None,
+                    amount: format_amount(amount),
+                });
+            }
+            _ => {
+                println!("Unrecognized type found in .CSV : \"{}\".", csv_type);
+
+                let generic = QifAction::Generic {
+                    date,
+                    payee: self.description.clone(),
+                    memo: Some(memo),
+                    category: None,
+                    amount: format_amount(amount),
+                };
+                println!("Entering as cash transaction.");
+                println!("{:#?}", generic);
+
+                res.push(generic);
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_trades_in_foreign_currency() -> Result<()> {
+        let csv = IbkrTradesReader {}.csv_header()
+            + "\n"
+            + r#""U1","EUR","1.1","STK","SAP","SAP SE","","1","","","","20240115","10","100","-2","-1002","BUY","O""#
+            + "\n"
+            + r#""U1","USD","1","OPT","AAPL  240119C00150000","AAPL 19JAN24 150 C","AAPL","100","150","20240119","C","20240116","-1","2.5","-1","249","SELL","O""#
+            + "\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = IbkrTradesReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 2);
+
+        // csv order is reversed.
+        match &actions[0] {
+            QifAction::ShtSell { trade } => {
+                assert_eq!(trade.symbol, "AAPL  240119C00150000");
+                assert_eq!(trade.quantity, "100");
+                assert_eq!(trade.amount, "249.00");
+                assert_eq!(trade.memo, None);
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.price, "110");
+                assert_eq!(trade.amount, "1102.20");
+                assert_eq!(trade.fees, "2.20");
+                assert_eq!(trade.memo, Some("converted from EUR at 1.1".to_string()));
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cash_in_foreign_currency() -> Result<()> {
+        let csv = IbkrCashReader {}.csv_header()
+            + "\n"
+            + r#""U1","EUR","1.1","STK","SAP","SAP SE","","1","","","","20240115","20","Dividends""#
+            + "\n"
+            + r#""U1","EUR","1.1","STK","SAP","SAP SE WITHHOLDING","","1","","","","20240115","-5","Withholding Tax""#
+            + "\n"
+            + r#""U1","USD","1","","","USD DEBIT INT FOR JAN-2024","","","","","","20240203","-12.5","Broker Interest Paid""#
+            + "\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = IbkrCashReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        match &actions[..] {
+            [QifAction::MargInt {
+                amount: interest, ..
+            }, QifAction::Generic {
+                memo: tax_memo,
+                amount: tax,
+                ..
+            }, QifAction::Div {
+                symbol,
+                amount: dividend,
+                ..
+            }] => {
+                assert_eq!(interest, "12.50");
+                assert_eq!(tax, "-5.50");
+                assert_eq!(
+                    tax_memo.as_deref(),
+                    Some("SAP SE WITHHOLDING (converted from EUR at 1.1)")
+                );
+                assert_eq!(symbol, "SAP");
+                assert_eq!(dividend, "22.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,538 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// Kraken ledger export.
+//
+// A trade is two ledger rows sharing a refid, one for each asset.   When one side is
+// dollars the trade is a buy or sell of the other side, otherwise it is entered as a sell
+// of one asset and a buy of the other.   Only dollars are cash, other fiat currencies
+// (EUR, GBP ...) are entered as securities like the crypto assets.   The ledger has no
+// prices, so trades without dollars are valued at the dollar price of the nearest (in
+// time) trade of either asset for dollars in the same ledger, and entered without amounts
+// when there is none.   Staking and other rewards are valued the same way, deposits,
+// withdrawals and transfers are not.
+pub struct KrakenReader;
+
+impl Reader for KrakenReader {
+    fn csv_header(&self) -> String {
+        r#""txid","refid","time","type","subtype","aclass","asset","wallet","amount","fee","balance""#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d %H:%M:%S".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        ledger_to_transactions(bufreader, securities)
+    }
+}
+
+// ledgers exported before kraken added the wallet column.
+pub struct KrakenReaderNoWallet;
+
+impl Reader for KrakenReaderNoWallet {
+    fn csv_header(&self) -> String {
+        r#""txid","refid","time","type","subtype","aclass","asset","amount","fee","balance""#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d %H:%M:%S".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        ledger_to_transactions(bufreader, securities)
+    }
+}
+
+fn ledger_to_transactions(
+    bufreader: &mut dyn BufRead,
+    securities: &mut Option<Symbols>,
+) -> Result<Vec<QifAction>> {
+    let symbols = securities
+        .as_mut()
+        .ok_or(eyre!("Expected symbols but none provided."))?;
+
+    let mut rdr = csv::Reader::from_reader(bufreader);
+    let mut entries: Vec<KrakenEntry> = Vec::new();
+    for record in rdr.deserialize::<KrakenEntry>() {
+        if record.is_err() {
+            // some csv files are not too clean.
+            break;
+        }
+        let entry = record?;
+        // rows without txid are failed withdrawals and the like.
+        if !entry.txid.is_empty() {
+            entries.push(entry);
+        }
+    }
+
+    // trades paired by refid, other entries on their own.
+    let mut groups: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut done = vec![false; entries.len()];
+    for i in 0..entries.len() {
+        if done[i] {
+            continue;
+        }
+        done[i] = true;
+        let entry = &entries[i];
+        if entry.is_trade() {
+            let other = (i + 1..entries.len())
+                .find(|j| !done[*j] && entries[*j].refid == entry.refid && entries[*j].is_trade());
+            match other {
+                Some(j) => {
+                    done[j] = true;
+                    groups.push((i, Some(j)));
+                }
+                None => {
+                    println!("Other side of trade {} not found, skipping.", entry.refid);
+                }
+            }
+        } else {
+            groups.push((i, None));
+        }
+    }
+
+    let mut prices: Vec<SpotPrice> = Vec::new();
+    for (i, j) in &groups {
+        if let Some(j) = j {
+            if let Some(price) = entries[*i].dollar_price(&entries[*j])? {
+                prices.push(price);
+            }
+        }
+    }
+
+    let mut qif_actions: Vec<QifAction> = Vec::new();
+    for (i, j) in groups {
+        match j {
+            Some(j) => qif_actions.append(&mut entries[i].trade_to_qif_actions(
+                &entries[j],
+                &prices,
+                symbols,
+            )?),
+            None => qif_actions.append(&mut entries[i].to_qif_action(&prices, symbols)?),
+        }
+    }
+
+    // the ledger lists oldest entries first.
+    Ok(qif_actions)
+}
+
+// kraken's own names for the older assets, "XXBT" for bitcoin and "ZUSD" for dollars.
+const KRAKEN_ASSETS: &[(&str, &str)] = &[
+    ("XXBT", "BTC"),
+    ("XBT", "BTC"),
+    ("XXDG", "DOGE"),
+    ("XDG", "DOGE"),
+    ("XETH", "ETH"),
+    ("XETC", "ETC"),
+    ("XLTC", "LTC"),
+    ("XXLM", "XLM"),
+    ("XXMR", "XMR"),
+    ("XXRP", "XRP"),
+    ("XZEC", "ZEC"),
+    ("XREP", "REP"),
+    ("XMLN", "MLN"),
+    ("ZUSD", "USD"),
+    ("ZEUR", "EUR"),
+    ("ZGBP", "GBP"),
+    ("ZCAD", "CAD"),
+    ("ZJPY", "JPY"),
+    ("ZAUD", "AUD"),
+];
+
+// "XXBT" becomes "BTC", and staked or earning variants such as "DOT.S" or "ETH2.S" lose
+// their suffix.
+pub fn normalize_asset(asset: &str) -> String {
+    let asset = asset.split('.').next().unwrap_or(asset);
+    let asset = asset
+        .strip_suffix("2")
+        .filter(|a| *a == "ETH")
+        .unwrap_or(asset);
+    KRAKEN_ASSETS
+        .iter()
+        .find(|(kraken, _)| *kraken == asset)
+        .map(|(_, normal)| normal.to_string())
+        .unwrap_or_else(|| asset.to_string())
+}
+
+// the dollar price of an asset traded for dollars on a date.
+pub struct SpotPrice {
+    pub date: NaiveDate,
+    pub asset: String,
+    pub price: f64,
+}
+
+// the price of the asset from the trade for dollars closest to the date.
+fn spot_price(prices: &[SpotPrice], asset: &str, date: &NaiveDate) -> Option<f64> {
+    prices
+        .iter()
+        .filter(|spot| spot.asset == asset)
+        .min_by_key(|spot| (spot.date - *date).num_days().abs())
+        .map(|spot| spot.price)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KrakenEntry {
+    pub txid: String,
+    pub refid: String,
+    pub time: String,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub subtype: String,
+    pub asset: String,
+    pub amount: String,
+    pub fee: String,
+}
+
+impl KrakenEntry {
+    // times look like "2024-01-15 18:23:45".
+    fn get_date(&self) -> Result<NaiveDate> {
+        let date = self.time.get(..10).unwrap_or("");
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from kraken: {}", &self.time))
+    }
+
+    fn is_trade(&self) -> bool {
+        matches!(self.entry_type.as_str(), "trade" | "spend" | "receive")
+    }
+
+    fn asset(&self) -> String {
+        normalize_asset(&self.asset)
+    }
+
+    fn is_dollars(&self) -> bool {
+        self.asset() == "USD"
+    }
+
+    // the amount actually added to or taken from the balance.
+    fn net_amount(&self) -> Result<f64> {
+        Ok(parse_amount(&self.amount)? - parse_amount(&self.fee)?)
+    }
+
+    // the unsigned net quantity.   Kraken writes up to ten decimals, more than
+    // format_quantity keeps, so the amount is copied as is when there is no fee.
+    fn quantity(&self) -> Result<String> {
+        if parse_amount(&self.fee)? != 0.0 {
+            return Ok(format_quantity(self.net_amount()?.abs()));
+        }
+        let quantity = self.amount.trim().trim_start_matches('-');
+        if quantity.contains('.') {
+            Ok(quantity
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string())
+        } else {
+            Ok(quantity.to_string())
+        }
+    }
+
+    fn enter_symbol(&self, symbols: &mut Symbols) -> Result<String> {
+        let symbol = self.asset();
+        symbols.enter_if_not_found(&symbol, &symbol, &SecurityType::Crypto)?;
+        Ok(symbol)
+    }
+
+    // the price of a trade of crypto for dollars.
+    fn dollar_price(&self, other: &KrakenEntry) -> Result<Option<SpotPrice>> {
+        let (dollars, crypto) = match (self.asset().as_str(), other.asset().as_str()) {
+            ("USD", _) => (self, other),
+            (_, "USD") => (other, self),
+            _ => return Ok(None),
+        };
+        let quantity = crypto.net_amount()?;
+        if crypto.is_dollars() || quantity == 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(SpotPrice {
+            date: self.get_date()?,
+            asset: crypto.asset(),
+            price: (parse_amount(&dollars.amount)? / quantity).abs(),
+        }))
+    }
+
+    fn trade_to_qif_actions(
+        &self,
+        other: &KrakenEntry,
+        prices: &[SpotPrice],
+        symbols: &mut Symbols,
+    ) -> Result<Vec<QifAction>> {
+        let date = self.get_date()?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let (dollars, crypto) = match (self.is_dollars(), other.is_dollars()) {
+            (true, false) => (Some(self), other),
+            (false, true) => (Some(other), self),
+            _ => (None, self),
+        };
+
+        if let Some(dollars) = dollars {
+            let symbol = crypto.enter_symbol(symbols)?;
+            let quantity = crypto.net_amount()?;
+            let cash = dollars.net_amount()?;
+            let price = if quantity == 0.0 {
+                "".to_string()
+            } else {
+                format_quantity((parse_amount(&dollars.amount)? / quantity).abs())
+            };
+            let trade = Trade {
+                date,
+                symbol,
+                price,
+                quantity: crypto.quantity()?,
+                amount: format_amount(cash.abs()),
+                fees: format_amount(parse_amount(&dollars.fee)?),
+                memo: None,
+            };
+            if quantity > 0.0 {
+                res.push(QifAction::Buy { trade });
+            } else {
+                res.push(QifAction::Sell { trade });
+            }
+            return Ok(res);
+        }
+
+        let (sold, bought) = if self.net_amount()? < 0.0 {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let sold_quantity = sold.net_amount()?.abs();
+        let bought_quantity = bought.net_amount()?.abs();
+        let value = match spot_price(prices, &sold.asset(), &date) {
+            Some(price) => Some(price * sold_quantity),
+            None => spot_price(prices, &bought.asset(), &date).map(|price| price * bought_quantity),
+        };
+        if value.is_none() {
+            println!(
+                "No price for trade of {} for {} on {}, entering without amounts.",
+                sold.asset(),
+                bought.asset(),
+                self.time
+            );
+        }
+        let memo = Some(format!(
+            "Converted {} {} to {} {}",
+            sold.quantity()?,
+            sold.asset(),
+            bought.quantity()?,
+            bought.asset()
+        ));
+        for (entry, quantity, buy) in [
+            (sold, sold_quantity, false),
+            (bought, bought_quantity, true),
+        ] {
+            let (price, amount) = match value {
+                Some(value) if quantity != 0.0 => {
+                    (format_quantity(value / quantity), format_amount(value))
+                }
+                _ => ("".to_string(), "".to_string()),
+            };
+            let trade = Trade {
+                date,
+                symbol: entry.enter_symbol(symbols)?,
+                price,
+                quantity: entry.quantity()?,
+                amount,
+                fees: "".to_string(),
+                memo: memo.clone(),
+            };
+            if buy {
+                res.push(QifAction::Buy { trade });
+            } else {
+                res.push(QifAction::Sell { trade });
+            }
+        }
+        Ok(res)
+    }
+
+    fn to_qif_action(&self, prices: &[SpotPrice], symbols: &mut Symbols) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+        let date = self.get_date()?;
+        let amount = self.net_amount()?;
+
+        if self.is_dollars() {
+            res.push(QifAction::Generic {
+                date,
+                payee: "Kraken".to_string(),
+                memo: Some(self.entry_type.clone()),
+                category: None,
+                amount: format_amount(amount),
+            });
+            return Ok(res);
+        }
+
+        match self.entry_type.as_str() {
+            // moves between the spot and staking wallets.
+            "transfer" if self.subtype.contains("staking") || self.subtype.contains("spot") => {}
+            // rewards have the value received as cost basis.
+            "staking" | "earn" | "reward" if amount > 0.0 => {
+                let symbol = self.enter_symbol(symbols)?;
+                let (price, value) = match spot_price(prices, &symbol, &date) {
+                    Some(price) => (
+                        Some(format_quantity(price)),
+                        Some(format_amount(price * amount)),
+                    ),
+                    None => {
+                        println!(
+                            "No price for {} {} on {}, entering without amount.",
+                            self.entry_type, symbol, self.time
+                        );
+                        (None, None)
+                    }
+                };
+                res.push(QifAction::ShrsIn {
+                    date,
+                    symbol,
+                    quantity: self.quantity()?,
+                    price,
+                    amount: value,
+                });
+            }
+            "staking" | "earn" | "reward" | "deposit" | "withdrawal" | "transfer" => {
+                if amount == 0.0 {
+                    return Ok(res);
+                }
+                let symbol = self.enter_symbol(symbols)?;
+                let quantity = self.quantity()?;
+                if amount > 0.0 {
+                    res.push(QifAction::ShrsIn {
+                        date,
+                        symbol,
+                        quantity,
+                        price: None,
+                        amount: None,
+                    });
+                } else {
+                    res.push(QifAction::ShrsOut {
+                        date,
+                        symbol,
+                        quantity,
+                        price: None,
+                        amount: None,
+                    });
+                }
+            }
+            _ => {
+                println!("Ledger type not handled : \"{}\".", self.entry_type);
+                println!("{:#?}", self);
+                println!();
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_normalize_asset() {
+        assert_eq!(normalize_asset("XXBT"), "BTC");
+        assert_eq!(normalize_asset("ZUSD"), "USD");
+        assert_eq!(normalize_asset("DOT.S"), "DOT");
+        assert_eq!(normalize_asset("ETH2.S"), "ETH");
+        assert_eq!(normalize_asset("SOL"), "SOL");
+    }
+
+    #[test]
+    fn test_kraken() -> Result<()> {
+        let csv = KrakenReader {}.csv_header()
+            + "\n"
+            + r#""L1","T1","2024-01-15 10:00:00","trade","","currency","ZUSD","spot / main",-100.0000,0.2600,900.0000"#
+            + "\n"
+            + r#""L2","T1","2024-01-15 10:00:00","trade","","currency","XXBT","spot / main",0.0025000000,0.0000000000,0.0025000000"#
+            + "\n"
+            + r#""L3","T2","2024-01-16 10:00:00","staking","","currency","XXBT","earn / bonded",0.0001000000,0.0000000000,0.0026000000"#
+            + "\n"
+            + r#""L4","T3","2024-01-17 10:00:00","trade","","currency","XXBT","spot / main",-0.0010000000,0.0000000000,0.0015000000"#
+            + "\n"
+            + r#""L5","T3","2024-01-17 10:00:00","trade","","currency","XETH","spot / main",0.0200000000,0.0000500000,0.0199500000"#
+            + "\n"
+            + r#""L6","D1","2024-01-18 10:00:00","deposit","","currency","ZEUR","spot / main",50.0000,0.0000,50.0000"#
+            + "\n"
+            + r#""L7","T4","2024-01-18 11:00:00","trade","","currency","ZEUR","spot / main",-36.8000,0.0000,13.2000"#
+            + "\n"
+            + r#""L8","T4","2024-01-18 11:00:00","trade","","currency","XXBT","spot / main",0.0010000000,0.0000000000,0.0025000000"#
+            + "\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = KrakenReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 7);
+
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.symbol, "BTC");
+                assert_eq!(trade.quantity, "0.0025");
+                assert_eq!(trade.price, "40000");
+                assert_eq!(trade.amount, "100.26");
+                assert_eq!(trade.fees, "0.26");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::ShrsIn {
+                symbol,
+                quantity,
+                amount,
+                ..
+            } => {
+                assert_eq!(symbol, "BTC");
+                assert_eq!(quantity, "0.0001");
+                // valued at the price of the bitcoin bought for dollars.
+                assert_eq!(amount.as_deref(), Some("4.00"));
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match (&actions[2], &actions[3]) {
+            (QifAction::Sell { trade: sell }, QifAction::Buy { trade: buy }) => {
+                assert_eq!(sell.symbol, "BTC");
+                assert_eq!(sell.quantity, "0.001");
+                // valued at the price of the bitcoin bought for dollars.
+                assert_eq!(sell.price, "40000");
+                assert_eq!(sell.amount, "40.00");
+                assert_eq!(buy.symbol, "ETH");
+                assert_eq!(buy.quantity, "0.01995");
+                assert_eq!(buy.amount, "40.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        // euros are a security, not cash.
+        match (&actions[4], &actions[5], &actions[6]) {
+            (
+                QifAction::ShrsIn { symbol, amount, .. },
+                QifAction::Sell { trade: sell },
+                QifAction::Buy { trade: buy },
+            ) => {
+                assert_eq!(symbol, "EUR");
+                assert!(amount.is_none());
+                assert_eq!(sell.symbol, "EUR");
+                assert_eq!(sell.quantity, "36.8");
+                assert_eq!(buy.symbol, "BTC");
+                assert_eq!(buy.amount, "40.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
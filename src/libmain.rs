@@ -2,15 +2,40 @@ use std::env;
 use std::ffi::OsString;
 use structopt::StructOpt;
 
-// use crate::fidelity_reader::FidelityReader;
+use crate::amex_reader::AmexReader;
+use crate::capital_one_reader::CapitalOneReader;
+use crate::chase_reader::{ChaseCardReader, ChaseCheckingReader};
+use crate::citi_reader::CitiReader;
+use crate::coinbase_reader::CoinbaseReader;
+use crate::empower_reader::EmpowerReader;
+use crate::etrade_reader::{EtradeBenefitHistoryReader, EtradeReader};
+use crate::fidelity_reader::FidelityReader;
 use crate::file_names::FileNames;
 use crate::file_to_memory;
-use crate::opt::Opt;
+use crate::ibkr_reader::{IbkrCashReader, IbkrTradesReader};
+use crate::kraken_reader::{KrakenReader, KrakenReaderNoWallet};
+use crate::mint_reader::MintReader;
+use crate::netbenefits_reader::NetBenefitsReader;
+use crate::ofx_reader::OfxReader;
+use crate::opt::{Opt, OutputFormat};
+use crate::paypal_reader::PaypalReader;
+use crate::profile_reader::ProfileReader;
+use crate::qif_reader::QifReader;
 use crate::readers::Readers;
+use crate::robinhood_reader::RobinhoodReader;
+use crate::schwab_eac_reader::SchwabEacReader;
+use crate::schwab_reader::SchwabJsonReader;
 use crate::schwab_reader::SchwabReader;
 use crate::schwab_reader::SchwabReaderOldCsv;
-use crate::sofi_reader::SoFiReader;
+use crate::script_reader::ScriptReader;
+use crate::sofi_reader::{SoFiInvestReader, SoFiReader};
+use crate::spreadsheet;
 use crate::symbols::Symbols;
+use crate::treasury_direct_reader::TreasuryDirectReader;
+use crate::vanguard_reader::VanguardReader;
+use crate::venmo_reader::VenmoReader;
+use crate::wise_reader::WiseReader;
+use crate::ynab_reader::YnabReader;
 use stable_eyre::eyre::*;
 
 pub fn libmain<I>(iter: I) -> Result<()>
@@ -23,19 +48,76 @@ where
 
     env::set_current_dir(&file_names.workdir)?;
 
+    let amex_reader = AmexReader {
+        card_member_class: opts.card_member_class,
+    };
+
+    let profile_readers = match &opts.profiles {
+        None => Vec::new(),
+        Some(dir) => ProfileReader::load_dir(dir)?,
+    };
+    let script_readers = match &opts.scripts {
+        None => Vec::new(),
+        Some(dir) => ScriptReader::load_dir(dir)?,
+    };
+
     let mut readers = Readers::new();
 
     readers.register(&SchwabReader {});
-    readers.register(&SchwabReaderOldCsv {});   // just in case.
-    //    readers.register(&FidelityReader {});
+    readers.register(&SchwabReaderOldCsv {}); // just in case.
+    readers.register(&SchwabEacReader {});
+    readers.register(&SchwabJsonReader {});
+    readers.register(&FidelityReader {});
+    readers.register(&NetBenefitsReader {});
     readers.register(&SoFiReader {});
+    readers.register(&SoFiInvestReader {});
+    readers.register(&ChaseCheckingReader {});
+    readers.register(&ChaseCardReader {});
+    readers.register(&amex_reader);
+    readers.register(&CapitalOneReader {});
+    readers.register(&CitiReader {});
+    readers.register(&PaypalReader {});
+    readers.register(&VenmoReader {});
+    readers.register(&WiseReader {});
+    readers.register(&MintReader {});
+    readers.register(&EmpowerReader {});
+    readers.register(&YnabReader {});
+    readers.register(&VanguardReader {});
+    readers.register(&IbkrTradesReader {});
+    readers.register(&IbkrCashReader {});
+    readers.register(&RobinhoodReader {});
+    readers.register(&EtradeReader {});
+    readers.register(&EtradeBenefitHistoryReader {});
+    readers.register(&CoinbaseReader {});
+    readers.register(&KrakenReader {});
+    readers.register(&KrakenReaderNoWallet {}); // just in case.
+    readers.register(&TreasuryDirectReader {});
+    readers.register(&OfxReader {});
+    readers.register(&QifReader {});
 
-    let mut bufreader = file_to_memory::read_file_to_cursor(&opts.transactions)?;
+    // registered last so that a profile or script can replace a built in reader with the
+    // same header.
+    for profile_reader in &profile_readers {
+        readers.register(profile_reader);
+    }
+    for script_reader in &script_readers {
+        readers.register(script_reader);
+    }
+
+    let mut bufreader = if spreadsheet::is_spreadsheet(&opts.transactions) {
+        spreadsheet::read_spreadsheet_to_cursor(
+            &opts.transactions,
+            &opts.worksheet,
+            &readers.csv_headers(),
+        )?
+    } else {
+        file_to_memory::read_file_to_cursor(&opts.transactions)?
+    };
 
     let optional_reader = readers.identify_reader(&mut bufreader)?;
 
     let reader = optional_reader.ok_or(eyre!(
-        "No recognized csv header found in file : {:#?}",
+        "No recognized csv header or contents found in file : {:#?}",
         &opts.transactions
     ))?;
 
@@ -53,9 +135,33 @@ where
             )
         })?;
 
-    transactions
-        .print_qifs(&file_names, &opts.cash_acct)
-        .with_context(|| "unable to create qif files. ".to_string())?;
+    match opts.output_format {
+        OutputFormat::Qif => transactions
+            .print_qifs(&file_names, &opts.cash_acct)
+            .with_context(|| "unable to create qif files. ".to_string())?,
+        OutputFormat::Qfx => transactions
+            .print_qfxs(&file_names, &opts.account_id, &opts.currency)
+            .with_context(|| "unable to create qfx files. ".to_string())?,
+        OutputFormat::Ledger => {
+            // the name of the input file when no account is given.
+            let account = opts.ledger_acct.clone().unwrap_or_else(|| {
+                opts.transactions
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            });
+            transactions
+                .print_ledger(
+                    &file_names,
+                    &account,
+                    &opts.cash_acct,
+                    &opts.income_acct,
+                    &opts.expense_acct,
+                )
+                .with_context(|| "unable to create journal file. ".to_string())?
+        }
+    }
 
     Ok(())
 }
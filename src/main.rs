@@ -3,20 +3,50 @@ extern crate structopt;
 use crate::libmain::libmain;
 use stable_eyre::eyre::*;
 
+mod action_builder;
+mod amex_reader;
+mod amounts;
+mod capital_one_reader;
+mod chase_reader;
+mod citi_reader;
+mod coinbase_reader;
 mod csv_reader;
-// mod fidelity_reader;
+mod empower_reader;
+mod etrade_reader;
+mod fidelity_reader;
 mod file_names;
 mod file_to_memory;
 mod find_matching_line;
+mod ibkr_reader;
+mod kraken_reader;
 mod libmain;
+mod mint_reader;
+mod netbenefits_reader;
+mod ofx_reader;
 mod opt;
+mod option_symbol;
+mod paypal_reader;
+mod profile_reader;
+mod qif_reader;
 mod readers;
+mod robinhood_reader;
+mod schwab_eac_reader;
 mod schwab_reader;
+mod script_reader;
 mod security;
 mod sofi_reader;
+mod split_amount;
+mod spreadsheet;
 mod symbols;
 mod transaction;
+mod transactions_ledger;
+mod transactions_qfx;
 mod transactions_qif;
+mod treasury_direct_reader;
+mod vanguard_reader;
+mod venmo_reader;
+mod wise_reader;
+mod ynab_reader;
 
 fn main() -> Result<()> {
     stable_eyre::install()?;
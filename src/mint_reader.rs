@@ -0,0 +1,134 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, parse_amount};
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Mint transactions.csv, holding every account linked to mint.   The transactions are
+// split by account name, with one output .qif per account.
+pub struct MintReader;
+
+impl Reader for MintReader {
+    fn csv_header(&self) -> String {
+        r#""Date","Description","Original Description","Amount","Transaction Type","Category","Account Name","Labels","Notes""#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<MintTransaction>(bufreader, securities)
+    }
+
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        <dyn Reader>::from_csv_by_account::<MintTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MintTransaction {
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Transaction Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+    #[serde(rename = "Account Name")]
+    pub account_name: String,
+    #[serde(rename = "Notes")]
+    pub notes: String,
+}
+
+impl MintTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from mint: {}", &self.date))
+    }
+
+    // amounts are unsigned, the transaction type tells debits from credits.
+    fn amount(&self) -> Result<String> {
+        let amount = parse_amount(&self.amount)?.abs();
+        Ok(if self.transaction_type == "debit" {
+            format_amount(-amount)
+        } else {
+            format_amount(amount)
+        })
+    }
+}
+
+impl Transaction for MintTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        Ok(vec![QifAction::Generic {
+            date: self.get_date()?,
+            payee: self.description.clone(),
+            memo: if self.notes.is_empty() {
+                None
+            } else {
+                Some(self.notes.clone())
+            },
+            category: if self.category.is_empty() || self.category == "Uncategorized" {
+                None
+            } else {
+                Some(self.category.clone())
+            },
+            amount: self.amount()?,
+        }])
+    }
+}
+
+impl AccountTransaction for MintTransaction {
+    fn account(&self) -> String {
+        self.account_name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_mint() -> Result<()> {
+        let csv = MintReader {}.csv_header()
+            + "\n"
+            + "\"1/16/2024\",\"Grocer\",\"GROCER #12\",\"12.34\",\"debit\",\"Groceries\",\"Visa\",\"\",\"\"\n"
+            + "\"1/15/2024\",\"Payroll\",\"ACME PAYROLL\",\"1000.00\",\"credit\",\"Paycheck\",\"Checking\",\"\",\"January\"\n"
+            + "\"1/14/2024\",\"Coffee\",\"COFFEE\",\"4.50\",\"debit\",\"Uncategorized\",\"Visa\",\"\",\"\"\n";
+        let accounts =
+            MintReader {}.to_transactions_by_account(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].0, "Checking");
+        assert_eq!(accounts[1].0, "Visa");
+        match &accounts[1].1[..] {
+            [QifAction::Generic {
+                payee: first,
+                category: None,
+                ..
+            }, QifAction::Generic {
+                category, amount, ..
+            }] => {
+                assert_eq!(first, "Coffee");
+                assert_eq!(category.as_deref(), Some("Groceries"));
+                assert_eq!(amount, "-12.34");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{clean_amount, format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Fidelity NetBenefits 401(k) transaction history, meant for -a Retirement.
+//
+// Funds in a plan often have no ticker, so the fund name is used as symbol.   Employee
+// contributions are buys, paid from the linked account if there is one (the paycheck).
+// Employer contributions are entered as income to the fund followed by a buy with that
+// cash, dividends and interest as reinvested, and revenue credits (fees refunded by
+// the plan) as shares in.
+pub struct NetBenefitsReader;
+
+impl Reader for NetBenefitsReader {
+    fn csv_header(&self) -> String {
+        r#"Date,Investment,Transaction Type,Source,Amount,Shares/Unit,Price"#.to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<NetBenefitsTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetBenefitsTransaction {
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Investment")]
+    pub investment: String,
+    #[serde(rename = "Transaction Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Source")]
+    pub source: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Shares/Unit")]
+    pub shares: String,
+    #[serde(rename = "Price")]
+    pub price: String,
+}
+
+impl NetBenefitsTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from netbenefits: {}", &self.date))
+    }
+
+    fn is_employer_source(&self) -> bool {
+        let source = self.source.to_lowercase();
+        source.contains("employer") || source.contains("match") || source.contains("company")
+    }
+
+    fn memo(&self) -> String {
+        if self.source.is_empty() {
+            self.transaction_type.clone()
+        } else {
+            format!("{} : {}", self.transaction_type, self.source)
+        }
+    }
+
+    fn quantity(&self) -> Result<String> {
+        Ok(format_quantity(parse_amount(&self.shares)?.abs()))
+    }
+
+    fn amount(&self) -> Result<String> {
+        Ok(format_amount(parse_amount(&self.amount)?.abs()))
+    }
+
+    fn to_trade(&self) -> Result<Trade> {
+        Ok(Trade {
+            date: self.get_date()?,
+            symbol: self.investment.clone(),
+            price: clean_amount(&self.price),
+            quantity: self.quantity()?,
+            amount: self.amount()?,
+            fees: "".to_string(),
+            memo: Some(self.memo()),
+        })
+    }
+
+    // income to the fund, then a buy of the fund with it.
+    fn to_income_and_buy(&self) -> Result<Vec<QifAction>> {
+        Ok(vec![
+            QifAction::MiscInc {
+                date: self.get_date()?,
+                symbol: self.investment.clone(),
+                memo: self.memo(),
+                amount: self.amount()?,
+            },
+            QifAction::BuyUnlinked {
+                trade: self.to_trade()?,
+            },
+        ])
+    }
+
+    fn to_shares(&self, shares_in: bool) -> Result<QifAction> {
+        let date = self.get_date()?;
+        let symbol = self.investment.clone();
+        let quantity = self.quantity()?;
+        let price = Some(clean_amount(&self.price));
+        let amount = Some(self.amount()?);
+        Ok(if shares_in {
+            QifAction::ShrsIn {
+                date,
+                symbol,
+                quantity,
+                price,
+                amount,
+            }
+        } else {
+            QifAction::ShrsOut {
+                date,
+                symbol,
+                quantity,
+                price,
+                amount,
+            }
+        })
+    }
+}
+
+impl Transaction for NetBenefitsTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        symbols.enter_if_not_found(
+            &self.investment,
+            &self.investment,
+            &SecurityType::MutualFund,
+        )?;
+
+        let csv_type = self.transaction_type.to_uppercase();
+        match csv_type.as_str() {
+            "CONTRIBUTION" => {
+                if self.is_employer_source() {
+                    res.append(&mut self.to_income_and_buy()?);
+                } else {
+                    res.push(QifAction::Buy {
+                        trade: self.to_trade()?,
+                    });
+                }
+            }
+            "DIVIDEND" | "REINVESTMENT" => {
+                res.push(QifAction::ReinvDiv {
+                    trade: self.to_trade()?,
+                });
+            }
+            "INTEREST" => {
+                res.push(QifAction::ReinvInt {
+                    trade: self.to_trade()?,
+                });
+            }
+            "EXCHANGE IN" | "TRANSFER IN" | "REVENUE CREDIT" => {
+                res.push(self.to_shares(true)?);
+            }
+            // fees are paid by selling shares.
+            "EXCHANGE OUT" | "TRANSFER OUT" | "RECORDKEEPING FEE" | "ADMINISTRATIVE FEE"
+            | "ADVISORY FEE" => {
+                res.push(self.to_shares(false)?);
+            }
+            "WITHDRAWAL" | "DISTRIBUTION" => {
+                res.push(QifAction::Sell {
+                    trade: self.to_trade()?,
+                });
+            }
+            _ => {
+                println!(
+                    "Unrecognized transaction type found in .CSV : \"{}\".",
+                    self.transaction_type
+                );
+                println!("{:#?}", self);
+                println!();
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_netbenefits() -> Result<()> {
+        let csv = NetBenefitsReader {}.csv_header()
+            + "\n"
+            + "03/28/2024,STABLE VALUE,INTEREST,,4.20,4.2,1.00\n"
+            + "03/28/2024,FID 500 INDEX,DIVIDEND,,16.80,0.1,168.00\n"
+            + "01/15/2024,FID 500 INDEX,CONTRIBUTION,Employer Match,125.00,0.75,166.67\n"
+            + "01/15/2024,FID 500 INDEX,CONTRIBUTION,Employee Pre-Tax,250.00,1.5,166.67\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = NetBenefitsReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 5);
+
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.amount, "250.00");
+                assert_eq!(
+                    trade.memo.as_deref(),
+                    Some("CONTRIBUTION : Employee Pre-Tax")
+                );
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match (&actions[1], &actions[2]) {
+            (QifAction::MiscInc { memo, amount, .. }, QifAction::BuyUnlinked { trade }) => {
+                assert_eq!(memo, "CONTRIBUTION : Employer Match");
+                assert_eq!(amount, "125.00");
+                assert_eq!(trade.quantity, "0.75");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        match (&actions[3], &actions[4]) {
+            (QifAction::ReinvDiv { trade: dividend }, QifAction::ReinvInt { trade: interest }) => {
+                assert_eq!(dividend.amount, "16.80");
+                assert_eq!(dividend.quantity, "0.1");
+                assert_eq!(interest.symbol, "STABLE VALUE");
+                assert_eq!(interest.amount, "4.20");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+
+        // the employer match buy is not linked, the employee contribution is.
+        let mut output: Vec<u8> = Vec::new();
+        let linked = Some("Paycheck".to_string());
+        for action in &actions {
+            action.print_transaction(&mut output, &linked, symbols.as_ref())?;
+        }
+        let output = String::from_utf8(output)?;
+        assert_eq!(output.matches("NBuyX").count(), 1);
+        assert_eq!(output.matches("L[Paycheck]").count(), 1);
+        assert!(output.contains("NMiscInc"));
+        Ok(())
+    }
+}
@@ -0,0 +1,653 @@
+use chrono::NaiveDate;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// OFX and QFX downloads, both version 1 (SGML, where elements holding a value need not be
+// closed) and version 2 (XML), for bank, credit card and investment statements.
+//
+// The securities of investment statements are listed once in the SECLIST, keyed by CUSIP,
+// and entered into the symbols with their ticker.   A file holding several statements
+// (checking and savings for example) is split by account id.
+pub struct OfxReader;
+
+#[derive(Debug, Default)]
+struct OfxElement {
+    name: String,
+    value: String,
+    children: Vec<OfxElement>,
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+impl OfxElement {
+    // Parses everything from the <OFX> tag on, the headers before it are ignored.
+    fn parse(contents: &str) -> Result<OfxElement> {
+        let start = contents
+            .find("<OFX>")
+            .ok_or(eyre!("No <OFX> tag found in ofx file."))?;
+        let mut rest = &contents[start..];
+        let mut stack: Vec<OfxElement> = vec![OfxElement::default()];
+
+        while let Some(open) = rest.find('<') {
+            let close = rest[open..]
+                .find('>')
+                .ok_or(eyre!("Unterminated tag in ofx file : {}", &rest[open..]))?
+                + open;
+            let tag = rest[open + 1..close].trim();
+            rest = &rest[close + 1..];
+
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                // close the element, and any sgml elements left open inside it.
+                let name = name.trim().to_uppercase();
+                if let Some(position) = stack.iter().skip(1).rposition(|e| e.name == name) {
+                    while stack.len() > position + 1 {
+                        let element = stack.pop().unwrap();
+                        stack.last_mut().unwrap().children.push(element);
+                    }
+                }
+                continue;
+            }
+
+            let (name, empty) = match tag.strip_suffix('/') {
+                Some(name) => (name.trim().to_uppercase(), true),
+                None => (tag.to_uppercase(), false),
+            };
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..text_end].trim();
+            if empty || !text.is_empty() {
+                // an element holding a value, closed in xml and usually not in sgml.
+                rest = &rest[text_end..];
+                let closing = format!("</{}>", name);
+                if rest.len() >= closing.len()
+                    && rest[..closing.len()].eq_ignore_ascii_case(&closing)
+                {
+                    rest = &rest[closing.len()..];
+                }
+                stack.last_mut().unwrap().children.push(OfxElement {
+                    name,
+                    value: decode_entities(text),
+                    children: Vec::new(),
+                });
+            } else {
+                stack.push(OfxElement {
+                    name,
+                    ..Default::default()
+                });
+            }
+        }
+
+        while stack.len() > 1 {
+            let element = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(element);
+        }
+        Ok(stack.pop().unwrap())
+    }
+
+    fn child(&self, name: &str) -> Option<&OfxElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    // the value found by following a path such as "INVTRAN/DTTRADE", empty if missing.
+    fn value(&self, path: &str) -> String {
+        let mut element = Some(self);
+        for name in path.split('/') {
+            element = element.and_then(|e| e.child(name));
+        }
+        element.map(|e| e.value.clone()).unwrap_or_default()
+    }
+
+    // the elements with one of the names, in document order, without looking inside them.
+    fn find_all<'a>(&'a self, names: &[&str], found: &mut Vec<&'a OfxElement>) {
+        for child in &self.children {
+            if names.contains(&child.name.as_str()) {
+                found.push(child);
+            } else {
+                child.find_all(names, found);
+            }
+        }
+    }
+
+    fn date(&self, path: &str) -> Result<NaiveDate> {
+        // 20240115, 20240115120000 or 20240115120000.000[-5:EST]
+        let value = self.value(path);
+        NaiveDate::parse_from_str(value.get(..8).unwrap_or(&value), "%Y%m%d")
+            .with_context(|| format!("Could not parse date from ofx {} : {}", path, value))
+    }
+
+    // ofx amounts have no thousands separators, but some banks use a decimal comma.
+    fn amount(&self, path: &str) -> Result<f64> {
+        parse_amount(&self.value(path).replace(',', "."))
+    }
+}
+
+// the ticker, name and type of each security, by CUSIP.
+struct OfxSecurities {
+    securities: HashMap<String, (String, String, SecurityType)>,
+}
+
+impl OfxSecurities {
+    fn new(ofx: &OfxElement) -> OfxSecurities {
+        let mut infos = Vec::new();
+        ofx.find_all(
+            &["STOCKINFO", "MFINFO", "OPTINFO", "DEBTINFO", "OTHERINFO"],
+            &mut infos,
+        );
+        let mut securities = HashMap::new();
+        for info in infos {
+            let security_type = match info.name.as_str() {
+                "MFINFO" => SecurityType::MutualFund,
+                "OPTINFO" => SecurityType::Option,
+                "DEBTINFO" => SecurityType::Bond,
+                _ => SecurityType::Stock,
+            };
+            let id = info.value("SECINFO/SECID/UNIQUEID");
+            let ticker = info.value("SECINFO/TICKER");
+            let symbol = if ticker.is_empty() {
+                id.clone()
+            } else {
+                ticker
+            };
+            let name = info.value("SECINFO/SECNAME");
+            let name = if name.is_empty() {
+                symbol.clone()
+            } else {
+                name
+            };
+            securities.insert(id, (symbol, name, security_type));
+        }
+        OfxSecurities { securities }
+    }
+
+    fn enter_all(&self, symbols: &mut Symbols) -> Result<()> {
+        for (symbol, name, security_type) in self.securities.values() {
+            symbols.enter_if_not_found(symbol, name, security_type)?;
+        }
+        Ok(())
+    }
+
+    // securities missing from the SECLIST are entered as stocks named by their CUSIP.
+    fn symbol(&self, transaction: &OfxElement, symbols: &mut Option<Symbols>) -> Result<String> {
+        let symbols = symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let id = transaction.value("SECID/UNIQUEID");
+        match self.securities.get(&id) {
+            Some((symbol, _, _)) => Ok(symbol.clone()),
+            None => {
+                symbols.enter_if_not_found(&id, &id, &SecurityType::Stock)?;
+                Ok(id)
+            }
+        }
+    }
+}
+
+fn memo(transaction: &OfxElement) -> Option<String> {
+    let memo = transaction.value("INVTRAN/MEMO");
+    if memo.is_empty() {
+        None
+    } else {
+        Some(memo)
+    }
+}
+
+// STMTTRN, from a bank or credit card statement or the cash of an investment account.
+fn to_generic(transaction: &OfxElement) -> Result<QifAction> {
+    let mut payee = transaction.value("NAME");
+    if payee.is_empty() {
+        payee = transaction.value("PAYEE/NAME");
+    }
+    let memo = transaction.value("MEMO");
+    if payee.is_empty() {
+        payee = memo.clone();
+    }
+    Ok(QifAction::Generic {
+        date: transaction.date("DTPOSTED")?,
+        payee: payee.clone(),
+        memo: if memo.is_empty() || memo == payee {
+            None
+        } else {
+            Some(memo)
+        },
+        category: None,
+        amount: format_amount(transaction.amount("TRNAMT")?),
+    })
+}
+
+// INVBUY and INVSELL, also found in REINVEST without the aggregate around them.
+fn to_trade(transaction: &OfxElement, symbol: String) -> Result<Trade> {
+    let fees: f64 = ["COMMISSION", "FEES", "TAXES", "LOAD"]
+        .iter()
+        .map(|name| transaction.amount(name))
+        .sum::<Result<f64>>()?;
+    Ok(Trade {
+        date: transaction.date("INVTRAN/DTTRADE")?,
+        symbol,
+        price: format_quantity(transaction.amount("UNITPRICE")?),
+        quantity: format_quantity(transaction.amount("UNITS")?.abs()),
+        amount: format_amount(transaction.amount("TOTAL")?.abs()),
+        fees: if fees == 0.0 {
+            "".to_string()
+        } else {
+            format_amount(fees)
+        },
+        memo: memo(transaction),
+    })
+}
+
+// option UNITS are contracts, entered as the shares they cover.
+fn to_option_trade(
+    transaction: &OfxElement,
+    aggregate: &OfxElement,
+    symbol: String,
+) -> Result<Trade> {
+    let shares_per_contract = match transaction.value("SHPERCTRCT").as_str() {
+        "" => 100.0,
+        _ => transaction.amount("SHPERCTRCT")?,
+    };
+    let trade = to_trade(aggregate, symbol)?;
+    Ok(Trade {
+        quantity: format_quantity(aggregate.amount("UNITS")?.abs() * shares_per_contract),
+        ..trade
+    })
+}
+
+fn to_investment_actions(
+    transaction: &OfxElement,
+    securities: &OfxSecurities,
+    symbols: &mut Option<Symbols>,
+) -> Result<Vec<QifAction>> {
+    let mut res: Vec<QifAction> = Vec::new();
+    match transaction.name.as_str() {
+        "BUYOPT" => {
+            let buy = transaction
+                .child("INVBUY")
+                .ok_or(eyre!("No INVBUY found in {}", transaction.name))?;
+            let trade = to_option_trade(transaction, buy, securities.symbol(buy, symbols)?)?;
+            if transaction.value("OPTBUYTYPE") == "BUYTOCLOSE" {
+                res.push(QifAction::CvrShrt { trade });
+            } else {
+                res.push(QifAction::Buy { trade });
+            }
+        }
+        "SELLOPT" => {
+            let sell = transaction
+                .child("INVSELL")
+                .ok_or(eyre!("No INVSELL found in {}", transaction.name))?;
+            let trade = to_option_trade(transaction, sell, securities.symbol(sell, symbols)?)?;
+            if transaction.value("OPTSELLTYPE") == "SELLTOOPEN" {
+                res.push(QifAction::ShtSell { trade });
+            } else {
+                res.push(QifAction::Sell { trade });
+            }
+        }
+        "BUYDEBT" | "BUYMF" | "BUYOTHER" | "BUYSTOCK" => {
+            let buy = transaction
+                .child("INVBUY")
+                .ok_or(eyre!("No INVBUY found in {}", transaction.name))?;
+            let trade = to_trade(buy, securities.symbol(buy, symbols)?)?;
+            if transaction.value("BUYTYPE") == "BUYTOCOVER" {
+                res.push(QifAction::CvrShrt { trade });
+            } else {
+                res.push(QifAction::Buy { trade });
+            }
+        }
+        "SELLDEBT" | "SELLMF" | "SELLOTHER" | "SELLSTOCK" => {
+            let sell = transaction
+                .child("INVSELL")
+                .ok_or(eyre!("No INVSELL found in {}", transaction.name))?;
+            let trade = to_trade(sell, securities.symbol(sell, symbols)?)?;
+            if transaction.value("SELLTYPE") == "SELLSHORT" {
+                res.push(QifAction::ShtSell { trade });
+            } else {
+                res.push(QifAction::Sell { trade });
+            }
+        }
+        "INCOME" => {
+            let date = transaction.date("INVTRAN/DTTRADE")?;
+            let symbol = securities.symbol(transaction, symbols)?;
+            let amount = format_amount(transaction.amount("TOTAL")?);
+            res.push(match transaction.value("INCOMETYPE").as_str() {
+                "DIV" => QifAction::Div {
+                    date,
+                    symbol,
+                    amount,
+                },
+                "INTEREST" => QifAction::IntInc {
+                    date,
+                    symbol,
+                    amount,
+                },
+                "CGLONG" => QifAction::CGLong {
+                    date,
+                    symbol,
+                    amount,
+                },
+                "CGSHORT" => QifAction::CGShort {
+                    date,
+                    symbol,
+                    amount,
+                },
+                _ => QifAction::MiscInc {
+                    date,
+                    symbol,
+                    memo: memo(transaction).unwrap_or_default(),
+                    amount,
+                },
+            });
+        }
+        "REINVEST" => {
+            let trade = to_trade(transaction, securities.symbol(transaction, symbols)?)?;
+            match transaction.value("INCOMETYPE").as_str() {
+                "DIV" => res.push(QifAction::ReinvDiv { trade }),
+                "INTEREST" => res.push(QifAction::ReinvInt { trade }),
+                "CGLONG" => res.push(QifAction::ReinvLg { trade }),
+                "CGSHORT" => res.push(QifAction::ReinvSh { trade }),
+                // quicken has no reinvestment of other income, enter the income then the buy.
+                _ => {
+                    res.push(QifAction::MiscInc {
+                        date: trade.date,
+                        symbol: trade.symbol.clone(),
+                        memo: trade.memo.clone().unwrap_or_default(),
+                        amount: trade.amount.clone(),
+                    });
+                    res.push(QifAction::BuyUnlinked { trade });
+                }
+            }
+        }
+        "TRANSFER" => {
+            let date = transaction.date("INVTRAN/DTTRADE")?;
+            let symbol = securities.symbol(transaction, symbols)?;
+            let quantity = format_quantity(transaction.amount("UNITS")?.abs());
+            let price = match transaction.value("UNITPRICE").as_str() {
+                "" => None,
+                _ => Some(format_quantity(transaction.amount("UNITPRICE")?)),
+            };
+            if transaction.value("TFERACTION") == "OUT" {
+                res.push(QifAction::ShrsOut {
+                    date,
+                    symbol,
+                    quantity,
+                    price,
+                    amount: None,
+                });
+            } else {
+                res.push(QifAction::ShrsIn {
+                    date,
+                    symbol,
+                    quantity,
+                    price,
+                    amount: None,
+                });
+            }
+        }
+        "MARGININTEREST" => {
+            res.push(QifAction::MargInt {
+                date: transaction.date("INVTRAN/DTTRADE")?,
+                memo: memo(transaction).unwrap_or_else(|| "Margin interest".to_string()),
+                amount: format_amount(transaction.amount("TOTAL")?.abs()),
+            });
+        }
+        "INVBANKTRAN" => {
+            if let Some(cash) = transaction.child("STMTTRN") {
+                res.push(to_generic(cash)?);
+            }
+        }
+        _ => {
+            println!(
+                "Unrecognized transaction type found in .OFX : \"{}\".",
+                transaction.name
+            );
+            println!("{:#?}", transaction);
+            println!();
+        }
+    };
+    Ok(res)
+}
+
+fn to_statement_actions(
+    statement: &OfxElement,
+    securities: &OfxSecurities,
+    symbols: &mut Option<Symbols>,
+) -> Result<Vec<QifAction>> {
+    let mut qif_actions: Vec<QifAction> = Vec::new();
+    match statement.child("INVTRANLIST") {
+        Some(transactions) => {
+            for transaction in &transactions.children {
+                qif_actions.append(&mut to_investment_actions(
+                    transaction,
+                    securities,
+                    symbols,
+                )?);
+            }
+        }
+        None => {
+            if let Some(transactions) = statement.child("BANKTRANLIST") {
+                for transaction in &transactions.children {
+                    if transaction.name == "STMTTRN" {
+                        qif_actions.push(to_generic(transaction)?);
+                    }
+                }
+            }
+            if statement.child("LEDGERBAL").is_some() {
+                println!(
+                    "Ledger balance of {} as of {}.",
+                    statement.value("LEDGERBAL/BALAMT"),
+                    statement.date("LEDGERBAL/DTASOF")?
+                );
+            }
+        }
+    }
+
+    // ofx does not say in what order transactions are listed.
+    qif_actions.sort_by_key(QifAction::date);
+    Ok(qif_actions)
+}
+
+impl Reader for OfxReader {
+    fn csv_header(&self) -> String {
+        String::new()
+    }
+
+    fn recognizes(&self, contents: &[u8]) -> bool {
+        contents.windows(5).any(|window| window == b"<OFX>")
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        Ok(self
+            .to_transactions_by_account(bufreader, securities)?
+            .into_iter()
+            .flat_map(|(_, actions)| actions)
+            .collect())
+    }
+
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        symbols: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        let mut bytes = Vec::new();
+        bufreader.read_to_end(&mut bytes)?;
+        // sgml files are often latin-1, which only matters for names and memos.
+        let contents = String::from_utf8_lossy(&bytes);
+        let ofx = OfxElement::parse(&contents)?;
+
+        let securities = OfxSecurities::new(&ofx);
+        if let Some(symbols) = symbols.as_mut() {
+            securities.enter_all(symbols)?;
+        }
+
+        let mut statements = Vec::new();
+        ofx.find_all(&["STMTRS", "CCSTMTRS", "INVSTMTRS"], &mut statements);
+        let mut res: Vec<(String, Vec<QifAction>)> = Vec::new();
+        for statement in &statements {
+            let account = if statements.len() == 1 {
+                String::new()
+            } else {
+                ["BANKACCTFROM", "CCACCTFROM", "INVACCTFROM"]
+                    .iter()
+                    .map(|from| statement.value(&format!("{}/ACCTID", from)))
+                    .find(|id| !id.is_empty())
+                    .unwrap_or_else(|| format!("account_{}", res.len() + 1))
+            };
+            res.push((
+                account,
+                to_statement_actions(statement, &securities, symbols)?,
+            ));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_ofx_sgml_bank() -> Result<()> {
+        let ofx = "OFXHEADER:100\nDATA:OFXSGML\nVERSION:102\n\n<OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n<CURDEF>USD\n<BANKACCTFROM><BANKID>123<ACCTID>9876<ACCTTYPE>CHECKING</BANKACCTFROM>\n<BANKTRANLIST><DTSTART>20240101\n<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240116120000.000[-5:EST]<TRNAMT>-12.34<FITID>2<NAME>AT&amp;T<MEMO>Phone</STMTTRN>\n<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240115<TRNAMT>1000,00<FITID>1<NAME>Payroll</STMTTRN>\n</BANKTRANLIST>\n<LEDGERBAL><BALAMT>987.66<DTASOF>20240131</LEDGERBAL>\n</STMTRS></STMTTRNRS></BANKMSGSRSV1>\n</OFX>\n";
+        assert!(OfxReader {}.recognizes(ofx.as_bytes()));
+        let actions = OfxReader {}.to_transactions(&mut Cursor::new(ofx), &mut None)?;
+        assert_eq!(actions.len(), 2);
+        match (&actions[0], &actions[1]) {
+            (
+                QifAction::Generic { amount, .. },
+                QifAction::Generic {
+                    date, payee, memo, ..
+                },
+            ) => {
+                assert_eq!(amount, "1000.00");
+                assert_eq!(*date, NaiveDate::from_ymd_opt(2024, 1, 16).unwrap());
+                assert_eq!(payee, "AT&T");
+                assert_eq!(memo.as_deref(), Some("Phone"));
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ofx_xml_investment() -> Result<()> {
+        let ofx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX>
+  <INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS>
+    <INVACCTFROM><BROKERID>broker.com</BROKERID><ACCTID>X1</ACCTID></INVACCTFROM>
+    <INVTRANLIST>
+      <BUYSTOCK>
+        <INVBUY>
+          <INVTRAN><FITID>1</FITID><DTTRADE>20240110</DTTRADE><MEMO></MEMO></INVTRAN>
+          <SECID><UNIQUEID>922908769</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+          <UNITS>10</UNITS><UNITPRICE>230.50</UNITPRICE><COMMISSION>1.00</COMMISSION>
+          <TOTAL>-2306.00</TOTAL>
+        </INVBUY>
+        <BUYTYPE>BUY</BUYTYPE>
+      </BUYSTOCK>
+      <REINVEST>
+        <INVTRAN><FITID>2</FITID><DTTRADE>20240320</DTTRADE></INVTRAN>
+        <SECID><UNIQUEID>922908769</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+        <INCOMETYPE>DIV</INCOMETYPE><TOTAL>-8.80</TOTAL><UNITS>0.037</UNITS><UNITPRICE>237.84</UNITPRICE>
+      </REINVEST>
+      <INCOME>
+        <INVTRAN><FITID>3</FITID><DTTRADE>20240320</DTTRADE></INVTRAN>
+        <SECID><UNIQUEID>922908769</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+        <INCOMETYPE>DIV</INCOMETYPE><TOTAL>8.80</TOTAL>
+      </INCOME>
+      <SELLOPT>
+        <INVSELL>
+          <INVTRAN><FITID>4</FITID><DTTRADE>20240322</DTTRADE></INVTRAN>
+          <SECID><UNIQUEID>AAPL240419C00200000</UNIQUEID><UNIQUEIDTYPE>OCC</UNIQUEIDTYPE></SECID>
+          <UNITS>-2</UNITS><UNITPRICE>1.50</UNITPRICE><COMMISSION>1.30</COMMISSION>
+          <TOTAL>298.70</TOTAL>
+        </INVSELL>
+        <OPTSELLTYPE>SELLTOOPEN</OPTSELLTYPE><SHPERCTRCT>100</SHPERCTRCT>
+      </SELLOPT>
+      <BUYOPT>
+        <INVBUY>
+          <INVTRAN><FITID>5</FITID><DTTRADE>20240405</DTTRADE></INVTRAN>
+          <SECID><UNIQUEID>AAPL240419C00200000</UNIQUEID><UNIQUEIDTYPE>OCC</UNIQUEIDTYPE></SECID>
+          <UNITS>2</UNITS><UNITPRICE>0.25</UNITPRICE><TOTAL>-50.00</TOTAL>
+        </INVBUY>
+        <OPTBUYTYPE>BUYTOCLOSE</OPTBUYTYPE>
+      </BUYOPT>
+    </INVTRANLIST>
+  </INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1>
+  <SECLISTMSGSRSV1><SECLIST>
+    <STOCKINFO><SECINFO>
+      <SECID><UNIQUEID>922908769</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+      <SECNAME>VANGUARD TOTAL STOCK MARKET ETF</SECNAME><TICKER>VTI</TICKER>
+    </SECINFO></STOCKINFO>
+    <OPTINFO><SECINFO>
+      <SECID><UNIQUEID>AAPL240419C00200000</UNIQUEID><UNIQUEIDTYPE>OCC</UNIQUEIDTYPE></SECID>
+      <SECNAME>AAPL Apr 19 2024 200.0 Call</SECNAME><TICKER>AAPL240419C00200000</TICKER>
+    </SECINFO><OPTTYPE>CALL</OPTTYPE><STRIKEPRICE>200</STRIKEPRICE><DTEXPIRE>20240419</DTEXPIRE><SHPERCTRCT>100</SHPERCTRCT></OPTINFO>
+  </SECLIST></SECLISTMSGSRSV1>
+</OFX>
+"#;
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = OfxReader {}.to_transactions(&mut Cursor::new(ofx), &mut symbols)?;
+        assert_eq!(actions.len(), 5);
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.symbol, "VTI");
+                assert_eq!(trade.quantity, "10");
+                assert_eq!(trade.price, "230.5");
+                assert_eq!(trade.amount, "2306.00");
+                assert_eq!(trade.fees, "1.00");
+                assert!(trade.memo.is_none());
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match (&actions[1], &actions[2]) {
+            (QifAction::ReinvDiv { trade }, QifAction::Div { amount, .. }) => {
+                assert_eq!(trade.amount, "8.80");
+                assert_eq!(trade.quantity, "0.037");
+                assert_eq!(amount, "8.80");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        // contracts are entered as the shares they cover.
+        match (&actions[3], &actions[4]) {
+            (QifAction::ShtSell { trade: open }, QifAction::CvrShrt { trade: close }) => {
+                assert_eq!(open.symbol, "AAPL240419C00200000");
+                assert_eq!(open.quantity, "200");
+                assert_eq!(open.price, "1.5");
+                assert_eq!(open.amount, "298.70");
+                assert_eq!(close.quantity, "200");
+                assert_eq!(close.amount, "50.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+
+        let mut output: Vec<u8> = Vec::new();
+        actions[1].print_transaction(&mut output, &Some("Cash".to_string()), symbols.as_ref())?;
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("NReinvDiv\n"));
+        assert!(!output.contains("L[Cash]"));
+        assert!(symbols.unwrap().lookup(&"VTI".to_string()).is_ok());
+        Ok(())
+    }
+}
@@ -10,6 +10,17 @@ arg_enum! {
     pub enum AccountType {
         Cash,
         Invest,
+        CreditCard,
+        Retirement,
+    }
+}
+
+arg_enum! {
+    #[derive(Debug, PartialEq)]
+    pub enum OutputFormat {
+        Qif,
+        Qfx,
+        Ledger,
     }
 }
 
@@ -23,6 +34,26 @@ pub struct Opt {
     pub cash_acct: Option<String>,
     #[structopt(short = "s", long = "securities", parse(from_os_str))]
     pub current_securities: Option<PathBuf>,
+    #[structopt(long = "card-member-class")]
+    pub card_member_class: bool,
+    #[structopt(long = "profiles", parse(from_os_str))]
+    pub profiles: Option<PathBuf>,
+    #[structopt(long = "scripts", parse(from_os_str))]
+    pub scripts: Option<PathBuf>,
+    #[structopt(long = "worksheet")]
+    pub worksheet: Option<String>,
+    #[structopt(long = "output-format", possible_values = &OutputFormat::variants(), case_insensitive = true, default_value = "Qif")]
+    pub output_format: OutputFormat,
+    #[structopt(long = "income-account", default_value = "Income")]
+    pub income_acct: String,
+    #[structopt(long = "expense-account", default_value = "Expenses")]
+    pub expense_acct: String,
+    #[structopt(long = "currency", default_value = "USD")]
+    pub currency: String,
+    #[structopt(long = "ledger-account")]
+    pub ledger_acct: Option<String>,
+    #[structopt(long = "account-id")]
+    pub account_id: Option<String>,
     #[structopt(parse(from_os_str))]
     pub transactions: PathBuf,
 }
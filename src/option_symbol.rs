@@ -0,0 +1,127 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use stable_eyre::eyre::*;
+
+// Builds the OCC style symbol that quicken uses for options, for example:
+//   "AAPL  240119C00150000"
+// underlying padded to 6, expiration as yymmdd, P or C, then strike as 5 digits of dollars
+// followed by 3 digits of fractional dollars.
+pub fn occ_symbol(
+    underlying: &str,
+    expiration: &NaiveDate,
+    put_or_call: &str,
+    strike: &str,
+) -> Result<String> {
+    let strike_re = Regex::new(
+        r"(?x)^
+                           ([\d]*)                 # dollars
+                           (?:\.([\d]*))?          # optional cents
+                           $",
+    )?;
+
+    let mut matched = false;
+    let mut strike_string = String::new();
+    for strike_cap in strike_re.captures_iter(strike) {
+        if matched {
+            return Err(eyre!("got multiple matches on strike"));
+        }
+        matched = true;
+        let dollars = &strike_cap[1];
+        let cents = strike_cap.get(2).map_or("", |m| m.as_str());
+        strike_string = format!("{:0>5}", dollars).to_string() + format!("{:0<3}", cents).as_str();
+    }
+    if !matched {
+        return Err(eyre!("got no matches on strike"));
+    }
+
+    let padded_symbol = format!("{: <6}", underlying);
+
+    Ok(padded_symbol
+        + expiration.format("%y%m%d").to_string().as_str()
+        + put_or_call
+        + strike_string.as_str())
+}
+
+// Reads an OCC style symbol back into its underlying, expiration, P or C and strike.
+pub fn parse_occ_symbol(symbol: &str) -> Option<(String, NaiveDate, String, f64)> {
+    let symbol_re = Regex::new(r"^(\S{1,6})\s*(\d{6})([CP])(\d{8})$").ok()?;
+    let symbol_cap = symbol_re.captures(symbol)?;
+    let expiration = NaiveDate::parse_from_str(&symbol_cap[2], "%y%m%d").ok()?;
+    let strike = symbol_cap[4].parse::<f64>().ok()? / 1000.0;
+    Some((
+        symbol_cap[1].to_string(),
+        expiration,
+        symbol_cap[3].to_string(),
+        strike,
+    ))
+}
+
+// Builds the security name used for options, for example:
+//   "CALL : APPLE INC - AAPL 01/19/2024 150.00 C"
+pub fn option_name(
+    put_or_call: &str,
+    underlying_description: &str,
+    underlying: &str,
+    expiration: &NaiveDate,
+    strike: &str,
+) -> String {
+    let kind = if put_or_call == "C" { "CALL" } else { "PUT" };
+    kind.to_string()
+        + " : "
+        + underlying_description.trim_end()
+        + " - "
+        + underlying
+        + " "
+        + expiration.format("%m/%d/%Y").to_string().as_str()
+        + " "
+        + strike
+        + " "
+        + put_or_call
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occ_symbol() -> Result<()> {
+        let expiration = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        assert_eq!(
+            occ_symbol("AAPL", &expiration, "C", "150.00")?,
+            "AAPL  240119C00150000"
+        );
+        assert_eq!(
+            occ_symbol("AAPL", &expiration, "P", "152.5")?,
+            "AAPL  240119P00152500"
+        );
+        assert_eq!(
+            occ_symbol("AAPL", &expiration, "C", "150")?,
+            "AAPL  240119C00150000"
+        );
+        assert!(occ_symbol("AAPL", &expiration, "C", "1x0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_occ_symbol() {
+        let expiration = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        assert_eq!(
+            parse_occ_symbol("AAPL  240119P00152500"),
+            Some(("AAPL".to_string(), expiration, "P".to_string(), 152.5))
+        );
+        assert_eq!(
+            parse_occ_symbol("AAPL240119C00150000"),
+            Some(("AAPL".to_string(), expiration, "C".to_string(), 150.0))
+        );
+        assert_eq!(parse_occ_symbol("AAPL"), None);
+    }
+
+    #[test]
+    fn test_option_name() {
+        let expiration = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        assert_eq!(
+            option_name("C", "APPLE INC ", "AAPL", &expiration, "150.00"),
+            "CALL : APPLE INC - AAPL 01/19/2024 150.00 C"
+        );
+    }
+}
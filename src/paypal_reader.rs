@@ -0,0 +1,326 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// PayPal "Activity download", all transactions, with the default columns.
+//
+// Besides payments the download holds rows that don't move money (authorizations and
+// their voids, marked "Memo" in the Balance Impact column), holds on the balance and
+// their releases, and a pair of "General Currency Conversion" rows for each payment in a
+// foreign currency.   Holds are left out, the conversion rows are folded into the payment
+// they refer to, and the PayPal fee is entered as a line of its own.
+pub struct PaypalReader;
+
+impl Reader for PaypalReader {
+    fn csv_header(&self) -> String {
+        r#""Date","Time","TimeZone","Name","Type","Status","Currency","Gross","Fee","Net","From Email Address","To Email Address","Transaction ID","Shipping Address","Address Status","Item Title","Item ID","Shipping and Handling Amount","Insurance Amount","Sales Tax","Option 1 Name","Option 1 Value","Option 2 Name","Option 2 Value","Reference Txn ID","Invoice Number","Custom Number","Quantity","Receipt ID","Balance","Address Line 1","Address Line 2/District/Neighborhood","Town/City","State/Province/Region/County/Territory/Prefecture/Republic","Zip/Postal Code","Country","Contact Phone Number","Subject","Note","Country Code","Balance Impact""#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        _securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+
+        let mut transactions: Vec<PaypalTransaction> = Vec::new();
+        for record in rdr.deserialize::<PaypalTransaction>() {
+            if record.is_err() {
+                // some csv files are not too clean.
+                break;
+            }
+            let transaction = record?;
+            if !transaction.is_skipped() {
+                transactions.push(transaction);
+            }
+        }
+
+        // conversion legs keyed by the transaction id of the payment they convert.
+        let mut conversions: HashMap<String, Vec<&PaypalTransaction>> = HashMap::new();
+        for transaction in transactions.iter().filter(|t| t.is_conversion()) {
+            conversions
+                .entry(transaction.reference_id.clone())
+                .or_default()
+                .push(transaction);
+        }
+
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        for transaction in transactions.iter().filter(|t| !t.is_conversion()) {
+            let legs = conversions.remove(&transaction.transaction_id);
+            qif_actions.append(&mut transaction.to_qif_action(legs.as_deref())?);
+        }
+
+        for (reference_id, legs) in conversions {
+            println!(
+                "Currency conversion without matching payment \"{}\", entering as is.",
+                reference_id
+            );
+            for leg in legs {
+                qif_actions.append(&mut leg.to_qif_action(None)?);
+            }
+        }
+
+        // paypal has changed the order of the download over time, sort rather than reverse.
+        qif_actions.sort_by_key(QifAction::date);
+        Ok(qif_actions)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaypalTransaction {
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Currency")]
+    pub currency: String,
+    #[serde(rename = "Gross")]
+    pub gross: String,
+    #[serde(rename = "Fee")]
+    pub fee: String,
+    #[serde(rename = "Transaction ID")]
+    pub transaction_id: String,
+    #[serde(rename = "Item Title")]
+    pub item_title: String,
+    #[serde(rename = "Reference Txn ID")]
+    pub reference_id: String,
+    #[serde(rename = "Balance Impact")]
+    pub balance_impact: String,
+}
+
+impl PaypalTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from paypal: {}", &self.date))
+    }
+
+    // authorizations, their voids and denied payments don't change the balance, and
+    // holds are always released again by a matching row.
+    fn is_skipped(&self) -> bool {
+        self.balance_impact == "Memo"
+            || self.status == "Denied"
+            || self.transaction_type.contains("Hold")
+            || self.transaction_type.contains("Authorization")
+    }
+
+    fn is_conversion(&self) -> bool {
+        self.transaction_type == "General Currency Conversion"
+    }
+
+    fn payee(&self) -> String {
+        if self.name.is_empty() {
+            self.transaction_type.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    fn memo(&self) -> Option<String> {
+        if self.item_title.is_empty() {
+            Some(self.transaction_type.clone())
+        } else {
+            Some(self.item_title.clone())
+        }
+    }
+
+    // with conversion legs the payment is entered in the currency of the conversion leg that
+    // is not the payment's own, at the rate paypal used.
+    fn conversion_rate(&self, legs: &[&PaypalTransaction]) -> Result<Option<(f64, String)>> {
+        let foreign = legs.iter().find(|leg| leg.currency == self.currency);
+        let base = legs.iter().find(|leg| leg.currency != self.currency);
+        match (foreign, base) {
+            (Some(foreign), Some(base)) => {
+                let foreign_amount = parse_amount(&foreign.gross)?.abs();
+                if foreign_amount == 0.0 {
+                    return Ok(None);
+                }
+                let rate = parse_amount(&base.gross)?.abs() / foreign_amount;
+                Ok(Some((rate, base.currency.clone())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn to_qif_action(&self, legs: Option<&[&PaypalTransaction]>) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+        let date = self.get_date()?;
+
+        let mut gross = parse_amount(&self.gross)?;
+        let mut fee = parse_amount(&self.fee)?;
+        let mut memo = self.memo();
+        if let Some((rate, base_currency)) = self.conversion_rate(legs.unwrap_or(&[]))? {
+            memo = Some(format!(
+                "{} : {} {} converted to {} at {}",
+                memo.unwrap_or_default(),
+                format_amount(gross),
+                self.currency,
+                base_currency,
+                format_quantity(rate)
+            ));
+            gross *= rate;
+            fee *= rate;
+        } else if legs.is_some() {
+            println!(
+                "Could not find conversion rate for transaction {}, entering as is.",
+                self.transaction_id
+            );
+        }
+
+        res.push(QifAction::Generic {
+            date,
+            payee: self.payee(),
+            memo,
+            category: None,
+            amount: format_amount(gross),
+        });
+        if fee != 0.0 {
+            res.push(QifAction::Generic {
+                date,
+                payee: "PayPal".to_string(),
+                memo: Some(format!("Fee : {}", self.payee())),
+                category: None,
+                amount: format_amount(fee),
+            });
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // date, name, type, currency, gross, fee, transaction id, item title, reference, impact.
+    fn row(fields: [&str; 10]) -> String {
+        let [date, name, transaction_type, currency, gross, fee, id, title, reference, impact] =
+            fields;
+        format!(
+            "\"{}\",\"10:00:00\",\"PST\",\"{}\",\"{}\",\"Completed\",\"{}\",\"{}\",\"{}\",\"\",\"\",\"\",\"{}\",\"\",\"\",\"{}\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"{}\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"{}\"\n",
+            date, name, transaction_type, currency, gross, fee, id, title, reference, impact
+        )
+    }
+
+    #[test]
+    fn test_paypal() -> Result<()> {
+        let csv = PaypalReader {}.csv_header()
+            + "\n"
+            + &*row([
+                "01/15/2024",
+                "Shop GmbH",
+                "General Authorization",
+                "EUR",
+                "-10.00",
+                "0.00",
+                "A1",
+                "Widget",
+                "",
+                "Memo",
+            ])
+            + &*row([
+                "01/16/2024",
+                "Shop GmbH",
+                "Express Checkout Payment",
+                "EUR",
+                "-10.00",
+                "0.00",
+                "P1",
+                "Widget",
+                "",
+                "Debit",
+            ])
+            + &*row([
+                "01/16/2024",
+                "",
+                "General Currency Conversion",
+                "EUR",
+                "10.00",
+                "0.00",
+                "C1",
+                "",
+                "P1",
+                "Credit",
+            ])
+            + &*row([
+                "01/16/2024",
+                "",
+                "General Currency Conversion",
+                "USD",
+                "-11.00",
+                "0.00",
+                "C2",
+                "",
+                "P1",
+                "Debit",
+            ])
+            + &*row([
+                "01/17/2024",
+                "Jane Doe",
+                "Website Payment",
+                "USD",
+                "100.00",
+                "-3.20",
+                "P2",
+                "Lessons",
+                "",
+                "Credit",
+            ]);
+        let actions = PaypalReader {}.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 3);
+
+        match &actions[0] {
+            QifAction::Generic {
+                payee,
+                memo,
+                amount,
+                ..
+            } => {
+                assert_eq!(payee, "Shop GmbH");
+                assert_eq!(
+                    memo.as_deref(),
+                    Some("Widget : -10.00 EUR converted to USD at 1.1")
+                );
+                assert_eq!(amount, "-11.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match (&actions[1], &actions[2]) {
+            (
+                QifAction::Generic {
+                    payee,
+                    memo,
+                    amount,
+                    ..
+                },
+                QifAction::Generic {
+                    payee: fee_payee,
+                    amount: fee,
+                    ..
+                },
+            ) => {
+                assert_eq!(payee, "Jane Doe");
+                assert_eq!(memo.as_deref(), Some("Lessons"));
+                assert_eq!(amount, "100.00");
+                assert_eq!(fee_payee, "PayPal");
+                assert_eq!(fee, "-3.20");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
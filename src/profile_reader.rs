@@ -0,0 +1,316 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+use std::result::Result::Ok;
+
+use crate::action_builder::{build_actions, ActionFields, ACTION_KINDS};
+use crate::amounts::{clean_amount, format_amount, parse_amount};
+use crate::csv_reader::*;
+use crate::split_amount::signed_amount;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// A reader configured by a toml profile instead of code, for banks too small to deserve a
+// reader of their own.   Every *.toml file in the directory given with --profiles is a
+// profile, see test_data/profiles for an example.
+//
+// The profile gives the csv header, which columns hold the date, payee, amount and so on,
+// how dates and numbers are written, and optionally a column whose values are mapped to
+// kinds of actions ("Buy", "Div", "Skip" ...).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub header: String,
+    pub date_format: String,
+    #[serde(default = "default_thousands_separator")]
+    pub thousands_separator: String,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    #[serde(default)]
+    pub invert_sign: bool, // for files with charges positive and payments negative.
+    #[serde(default = "default_newest_first")]
+    pub newest_first: bool,
+    pub columns: ProfileColumns,
+    pub action_column: Option<String>,
+    #[serde(default = "default_action")]
+    pub default_action: String,
+    #[serde(default)]
+    pub actions: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileColumns {
+    pub date: String,
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub category: Option<String>,
+    pub amount: Option<String>,
+    pub debit: Option<String>, // debit and credit replace amount for split amount columns.
+    pub credit: Option<String>,
+    pub symbol: Option<String>,
+    pub security_name: Option<String>,
+    pub quantity: Option<String>,
+    pub price: Option<String>,
+    pub fees: Option<String>,
+}
+
+fn default_thousands_separator() -> String {
+    ",".to_string()
+}
+
+fn default_decimal_separator() -> String {
+    ".".to_string()
+}
+
+fn default_newest_first() -> bool {
+    true
+}
+
+fn default_action() -> String {
+    "Generic".to_string()
+}
+
+impl Profile {
+    fn validate(&self) -> Result<()> {
+        if self.columns.amount.is_none()
+            && self.columns.debit.is_none()
+            && self.columns.credit.is_none()
+        {
+            return Err(eyre!(
+                "Either an amount or debit and credit columns are needed"
+            ));
+        }
+        for kind in self.actions.values().chain([&self.default_action]) {
+            if !ACTION_KINDS.contains(&kind.as_str()) {
+                return Err(eyre!(
+                    "Unrecognized action kind \"{}\", expected one of {:?}",
+                    kind,
+                    ACTION_KINDS
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // "1.234,56" with a "." thousands separator and a "," decimal separator is "1234.56".
+    fn normalize_number(&self, value: &str) -> String {
+        let mut value = value.to_string();
+        if !self.thousands_separator.is_empty() {
+            value = value.replace(&self.thousands_separator, "");
+        }
+        if self.decimal_separator != "." {
+            value = value.replace(&self.decimal_separator, ".");
+        }
+        clean_amount(&value)
+    }
+}
+
+pub struct ProfileReader {
+    pub name: String,
+    pub profile: Profile,
+}
+
+impl ProfileReader {
+    pub fn from_file(path: &Path) -> Result<ProfileReader> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read profile : {:#?}", path))?;
+        let profile: Profile = toml::from_str(&contents)
+            .with_context(|| format!("Unable to parse profile : {:#?}", path))?;
+        profile
+            .validate()
+            .with_context(|| format!("Invalid profile : {:#?}", path))?;
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        Ok(ProfileReader { name, profile })
+    }
+
+    // every *.toml file in the directory, in file name order.
+    pub fn load_dir(dir: &Path) -> Result<Vec<ProfileReader>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("Unable to read profiles directory : {:#?}", dir))?
+        {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| ProfileReader::from_file(path))
+            .collect()
+    }
+
+    fn to_fields(&self, row: &HashMap<&str, &str>) -> Result<Option<(String, ActionFields)>> {
+        let profile = &self.profile;
+        let columns = &profile.columns;
+        let field = |column: &Option<String>| {
+            column
+                .as_ref()
+                .and_then(|column| row.get(column.as_str()))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        let non_empty = |value: String| if value.is_empty() { None } else { Some(value) };
+
+        let date = field(&Some(columns.date.clone()));
+        // trailing totals or disclaimers.
+        if date.is_empty() {
+            return Ok(None);
+        }
+        let date = NaiveDate::parse_from_str(&date, &profile.date_format).with_context(|| {
+            format!(
+                "Could not parse date from profile {} with format {} : {}",
+                self.name, profile.date_format, date
+            )
+        })?;
+
+        let mut amount = if columns.amount.is_some() {
+            profile.normalize_number(&field(&columns.amount))
+        } else {
+            signed_amount(
+                &profile.normalize_number(&field(&columns.debit)),
+                &profile.normalize_number(&field(&columns.credit)),
+            )?
+        };
+        if profile.invert_sign {
+            amount = format_amount(-parse_amount(&amount)?);
+        }
+
+        let kind = match &profile.action_column {
+            Some(column) => {
+                let value = row.get(column.as_str()).map(|v| v.trim()).unwrap_or("");
+                profile
+                    .actions
+                    .get(value)
+                    .unwrap_or(&profile.default_action)
+                    .clone()
+            }
+            None => profile.default_action.clone(),
+        };
+
+        let fields = ActionFields {
+            date,
+            payee: field(&columns.payee),
+            memo: non_empty(field(&columns.memo)),
+            category: non_empty(field(&columns.category)),
+            amount,
+            symbol: field(&columns.symbol),
+            security_name: non_empty(field(&columns.security_name)),
+            quantity: profile.normalize_number(&field(&columns.quantity)),
+            price: profile.normalize_number(&field(&columns.price)),
+            fees: profile.normalize_number(&field(&columns.fees)),
+        };
+        Ok(Some((kind, fields)))
+    }
+}
+
+impl Reader for ProfileReader {
+    fn csv_header(&self) -> String {
+        self.profile.header.clone()
+    }
+
+    fn date_format(&self) -> String {
+        self.profile.date_format.clone()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+        let headers = rdr.headers()?.clone();
+
+        let mut qif_actions: Vec<Vec<QifAction>> = Vec::new();
+        for result in rdr.records() {
+            let record = match result {
+                Ok(record) => record,
+                // some csv files are not too clean.
+                Err(_) => break,
+            };
+            let row: HashMap<&str, &str> = headers.iter().zip(record.iter()).collect();
+            if let Some((kind, fields)) = self.to_fields(&row)? {
+                qif_actions.push(build_actions(&kind, &fields, securities)?);
+            }
+        }
+
+        if self.profile.newest_first {
+            qif_actions.reverse();
+        }
+        Ok(qif_actions.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_profile() -> Result<()> {
+        let readers = ProfileReader::load_dir(&PathBuf::from("test_data/profiles"))?;
+        assert_eq!(readers.len(), 1);
+        let reader = &readers[0];
+        assert_eq!(reader.name, "credit_union");
+
+        let csv = reader.csv_header()
+            + "\n"
+            + "17.01.2024,Card hold,HOLD,\"12,00\",,\"987,66\"\n"
+            + "16.01.2024,Payroll,DEPOSIT,,\"1.000,00\",\"987,66\"\n"
+            + "15.01.2024,Grocer,POS,\"12,34\",,\"-12,34\"\n";
+        let actions = reader.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 2);
+        match (&actions[0], &actions[1]) {
+            (
+                QifAction::Generic {
+                    payee,
+                    memo,
+                    amount,
+                    ..
+                },
+                QifAction::Generic {
+                    amount: deposit, ..
+                },
+            ) => {
+                assert_eq!(payee, "Grocer");
+                assert_eq!(memo.as_deref(), Some("POS"));
+                assert_eq!(amount, "-12.34");
+                assert_eq!(deposit, "1000.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_action_kind() {
+        let profile: Profile = toml::from_str(
+            r#"
+            header = "Date,Amount"
+            date_format = "%Y-%m-%d"
+            default_action = "Bye"
+            [columns]
+            date = "Date"
+            amount = "Amount"
+            "#,
+        )
+        .unwrap();
+        assert!(profile.validate().is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::clean_amount;
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// QIF exported by quicken or other tools, read back in to be cleaned up or converted.
+//
+// Bank, cash, credit card and investment transactions are read, including splits.   A
+// file exported with several accounts (each starting with an !Account record) is split
+// by account name, and the securities exported with it are entered into the symbols.
+// Investment transactions name their security, which is looked up by name.
+pub struct QifReader;
+
+const TRANSACTION_TYPES: &[&str] = &[
+    "!type:bank",
+    "!type:cash",
+    "!type:ccard",
+    "!type:invst",
+    "!type:oth a",
+    "!type:oth l",
+];
+
+// the lines of one record, up to its ^, by their first letter.
+struct QifRecord {
+    fields: Vec<(char, String)>,
+}
+
+impl QifRecord {
+    fn get(&self, code: char) -> String {
+        self.fields
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    }
+
+    fn optional(&self, code: char) -> Option<String> {
+        let value = self.get(code);
+        if value.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    // quicken writes dates as 1/16'24 or 1/16' 4, other tools as 01/16/2024.
+    fn date(&self) -> Result<NaiveDate> {
+        let date = self.get('D');
+        let date_re = Regex::new(r"^(\d{1,2})\s*/\s*(\d{1,2})\s*(['/])\s*(\d{1,4})$")?;
+        let cap = date_re
+            .captures(&date)
+            .ok_or(eyre!("Could not parse date from qif: {}", date))?;
+        let mut year: i32 = cap[4].parse()?;
+        if cap[4].len() <= 2 {
+            year += if &cap[3] == "'" { 2000 } else { 1900 };
+        }
+        NaiveDate::from_ymd_opt(year, cap[1].parse()?, cap[2].parse()?)
+            .ok_or(eyre!("Could not parse date from qif: {}", date))
+    }
+
+    fn amount(&self) -> String {
+        let amount = self.get('T');
+        if amount.is_empty() {
+            clean_amount(&self.get('U'))
+        } else {
+            clean_amount(&amount)
+        }
+    }
+
+    fn splits(&self) -> Vec<SplitLine> {
+        let mut splits: Vec<SplitLine> = Vec::new();
+        for (code, value) in &self.fields {
+            match code {
+                'S' => splits.push(SplitLine {
+                    category: value.clone(),
+                    memo: None,
+                    amount: String::new(),
+                }),
+                'E' => {
+                    if let Some(split) = splits.last_mut() {
+                        split.memo = Some(value.clone());
+                    }
+                }
+                '$' => {
+                    if let Some(split) = splits.last_mut() {
+                        split.amount = clean_amount(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        splits
+    }
+
+    fn to_bank_action(&self) -> Result<QifAction> {
+        let date = self.date()?;
+        let payee = self.get('P');
+        let memo = self.optional('M');
+        let amount = self.amount();
+        let splits = self.splits();
+        Ok(if splits.is_empty() {
+            QifAction::Generic {
+                date,
+                payee,
+                memo,
+                category: self.optional('L'),
+                amount,
+            }
+        } else {
+            QifAction::Split {
+                date,
+                payee,
+                memo,
+                amount,
+                splits,
+            }
+        })
+    }
+
+    // securities are named in investment transactions, unknown ones are entered as
+    // stocks with their name as symbol.
+    fn symbol(&self, symbols: &mut Option<Symbols>) -> Result<String> {
+        let symbols = symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let name = self.get('Y');
+        match symbols.find_symbol(&name) {
+            Some(symbol) => Ok(symbol),
+            None => {
+                symbols.enter_if_not_found(&name, &name, &SecurityType::Stock)?;
+                Ok(name)
+            }
+        }
+    }
+
+    fn to_trade(&self, symbols: &mut Option<Symbols>) -> Result<Trade> {
+        let memo = self.optional('M').filter(|memo| *memo != self.get('Y'));
+        Ok(Trade {
+            date: self.date()?,
+            symbol: self.symbol(symbols)?,
+            price: clean_amount(&self.get('I')),
+            quantity: clean_amount(&self.get('Q')),
+            amount: self.amount().trim_start_matches('-').to_string(),
+            fees: clean_amount(&self.get('O')),
+            memo,
+        })
+    }
+
+    fn to_invest_actions(&self, symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+        let action = self.get('N');
+        // the linked account of the export is replaced by the one given on the command line,
+        // actions without it keep their cash in the account.
+        let linked = action.ends_with('X');
+        let kind = action.trim_end_matches('X');
+        match kind {
+            "Buy" => {
+                let trade = self.to_trade(symbols)?;
+                if linked {
+                    res.push(QifAction::Buy { trade });
+                } else {
+                    res.push(QifAction::BuyUnlinked { trade });
+                }
+            }
+            "Sell" => res.push(QifAction::Sell {
+                trade: self.to_trade(symbols)?,
+            }),
+            "ShtSell" => res.push(QifAction::ShtSell {
+                trade: self.to_trade(symbols)?,
+            }),
+            "CvrShrt" => res.push(QifAction::CvrShrt {
+                trade: self.to_trade(symbols)?,
+            }),
+            "ReinvDiv" => res.push(QifAction::ReinvDiv {
+                trade: self.to_trade(symbols)?,
+            }),
+            "ReinvInt" => res.push(QifAction::ReinvInt {
+                trade: self.to_trade(symbols)?,
+            }),
+            "ReinvLg" => res.push(QifAction::ReinvLg {
+                trade: self.to_trade(symbols)?,
+            }),
+            "ReinvSh" => res.push(QifAction::ReinvSh {
+                trade: self.to_trade(symbols)?,
+            }),
+            "Div" | "IntInc" | "CGLong" | "CGShort" | "MiscInc" => {
+                let date = self.date()?;
+                let symbol = self.symbol(symbols)?;
+                let amount = self.amount();
+                res.push(match kind {
+                    "Div" => QifAction::Div {
+                        date,
+                        symbol,
+                        amount,
+                    },
+                    "IntInc" => QifAction::IntInc {
+                        date,
+                        symbol,
+                        amount,
+                    },
+                    "CGLong" => QifAction::CGLong {
+                        date,
+                        symbol,
+                        amount,
+                    },
+                    "CGShort" => QifAction::CGShort {
+                        date,
+                        symbol,
+                        amount,
+                    },
+                    _ => QifAction::MiscInc {
+                        date,
+                        symbol,
+                        memo: self.get('M'),
+                        amount,
+                    },
+                });
+            }
+            "ShrsIn" | "ShrsOut" => {
+                let date = self.date()?;
+                let symbol = self.symbol(symbols)?;
+                let quantity = clean_amount(&self.get('Q'));
+                let price = self.optional('I').map(|price| clean_amount(&price));
+                let amount = self.optional('T').map(|amount| clean_amount(&amount));
+                if kind == "ShrsIn" {
+                    res.push(QifAction::ShrsIn {
+                        date,
+                        symbol,
+                        quantity,
+                        price,
+                        amount,
+                    });
+                } else {
+                    res.push(QifAction::ShrsOut {
+                        date,
+                        symbol,
+                        quantity,
+                        price,
+                        amount,
+                    });
+                }
+            }
+            "MargInt" => res.push(QifAction::MargInt {
+                date: self.date()?,
+                memo: self.get('M'),
+                amount: self.amount(),
+            }),
+            // cash moving in and out of the account.
+            "XIn" | "Cash" | "Contrib" | "Withdrw" | "XOut" => {
+                let amount = self.amount();
+                let amount = if kind == "XOut" || kind == "Withdrw" {
+                    "-".to_string() + amount.trim_start_matches('-')
+                } else {
+                    amount
+                };
+                res.push(QifAction::Generic {
+                    date: self.date()?,
+                    payee: self.get('P'),
+                    memo: self.optional('M'),
+                    category: None,
+                    amount,
+                });
+            }
+            _ => {
+                println!(
+                    "Unrecognized investment action found in .QIF : \"{}\".",
+                    action
+                );
+                for (code, value) in &self.fields {
+                    println!("{}{}", code, value);
+                }
+                println!();
+            }
+        };
+        let linkable = matches!(
+            kind,
+            "Sell"
+                | "ShtSell"
+                | "CvrShrt"
+                | "MargInt"
+                | "Div"
+                | "IntInc"
+                | "CGLong"
+                | "CGShort"
+                | "MiscInc"
+        );
+        if linkable && !linked {
+            res = res
+                .into_iter()
+                .map(|action| QifAction::Unlinked {
+                    action: Box::new(action),
+                })
+                .collect();
+        }
+        Ok(res)
+    }
+
+    fn enter_security(&self, symbols: &mut Option<Symbols>) -> Result<()> {
+        if let Some(symbols) = symbols.as_mut() {
+            let symbol = self.get('S');
+            if !symbol.is_empty() {
+                let security_type =
+                    SecurityType::from_qif(&self.get('T')).unwrap_or(SecurityType::Stock);
+                symbols.enter_if_not_found(&symbol, &self.get('N'), &security_type)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Reader for QifReader {
+    fn csv_header(&self) -> String {
+        String::new()
+    }
+
+    fn recognizes(&self, contents: &[u8]) -> bool {
+        let contents = String::from_utf8_lossy(contents);
+        contents
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('!')
+            && contents
+                .lines()
+                .any(|line| TRANSACTION_TYPES.contains(&line.trim().to_lowercase().as_str()))
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        Ok(self
+            .to_transactions_by_account(bufreader, securities)?
+            .into_iter()
+            .flat_map(|(_, actions)| actions)
+            .collect())
+    }
+
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        symbols: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        let mut res: Vec<(String, Vec<QifAction>)> = Vec::new();
+        let mut section = String::new();
+        let mut account = String::new();
+        let mut fields: Vec<(char, String)> = Vec::new();
+
+        for line in bufreader.lines() {
+            let line = line?;
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            if line.starts_with('!') {
+                let header = line.to_lowercase();
+                if header == "!account" || header.starts_with("!type:") {
+                    section = header;
+                }
+                // !Option and !Clear lines change nothing here.
+                continue;
+            }
+            if line != "^" {
+                if let Some(code) = line.chars().next() {
+                    fields.push((code, line[code.len_utf8()..].to_string()));
+                }
+                continue;
+            }
+
+            let record = QifRecord {
+                fields: std::mem::take(&mut fields),
+            };
+            let mut actions = match section.as_str() {
+                "!account" => {
+                    account = record.get('N');
+                    continue;
+                }
+                "!type:security" => {
+                    record.enter_security(symbols)?;
+                    continue;
+                }
+                "!type:invst" => record.to_invest_actions(symbols)?,
+                _ if TRANSACTION_TYPES.contains(&section.as_str()) => {
+                    vec![record.to_bank_action()?]
+                }
+                // categories, classes, memorized transactions and prices.
+                _ => continue,
+            };
+            match res.iter_mut().find(|(name, _)| *name == account) {
+                Some((_, account_actions)) => account_actions.append(&mut actions),
+                None => res.push((account.clone(), actions)),
+            }
+        }
+
+        // a single account goes to the account given on the command line.
+        if res.len() == 1 {
+            res[0].0 = String::new();
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_qif_bank() -> Result<()> {
+        let qif = "!Option:AutoSwitch\n!Account\nNChecking\nTBank\n^\n!Clear:AutoSwitch\n\
+            !Type:Bank\nD1/16'24\nT-1,012.34\nPLandlord\nLRent\n^\n\
+            D01/17/2024\nT-100.00\nPGrocer\nSFood:Groceries\nEMilk\n$-60.00\nSHousehold\n$-40.00\n^\n\
+            !Account\nNVisa\nTCCard\n^\n!Type:CCard\nD1/18' 4\nT-5.00\nPCoffee\n^\n";
+        assert!(QifReader {}.recognizes(qif.as_bytes()));
+        let accounts = QifReader {}.to_transactions_by_account(&mut Cursor::new(qif), &mut None)?;
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].0, "Checking");
+        assert_eq!(accounts[0].1.len(), 2);
+        match &accounts[0].1[1] {
+            QifAction::Split { amount, splits, .. } => {
+                assert_eq!(amount, "-100.00");
+                assert_eq!(splits.len(), 2);
+                assert_eq!(splits[0].category, "Food:Groceries");
+                assert_eq!(splits[0].memo.as_deref(), Some("Milk"));
+                assert_eq!(splits[1].amount, "-40.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &accounts[1].1[0] {
+            QifAction::Generic { date, .. } => {
+                assert_eq!(*date, NaiveDate::from_ymd_opt(2004, 1, 18).unwrap())
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+
+        let mut output: Vec<u8> = Vec::new();
+        accounts[0].1[1].print_transaction(&mut output, &None, None)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "D1/17'24\nU-100.00\nT-100.00\nPGrocer\nSFood:Groceries\nEMilk\n$-60.00\nSHousehold\n$-40.00\n^\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_qif_invest() -> Result<()> {
+        let qif = "!Type:Security\nNVanguard Total Stock\nSVTI\nTStock\n^\n\
+            !Type:Invst\nD1/10'24\nNBuyX\nYVanguard Total Stock\nI230.50\nQ10\nT2,306.00\nO1.00\nL[Checking]\n$2,306.00\n^\n\
+            D3/20'24\nNDiv\nYApple Inc.\nT8.80\n^\n\
+            D3/21'24\nNSell\nYApple Inc.\nI190.00\nQ2\nT380.00\n^\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = QifReader {}.to_transactions(&mut Cursor::new(qif), &mut symbols)?;
+        assert_eq!(actions.len(), 3);
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.symbol, "VTI");
+                assert_eq!(trade.amount, "2306.00");
+                assert_eq!(trade.fees, "1.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::Unlinked { action } => match action.as_ref() {
+                QifAction::Div { symbol, amount, .. } => {
+                    assert_eq!(symbol, "AAPL");
+                    assert_eq!(amount, "8.80");
+                }
+                other => panic!("unexpected action: {:?}", other),
+            },
+            other => panic!("unexpected action: {:?}", other),
+        }
+
+        // without the X of the export, the cash stays in the account whatever is linked.
+        let mut output: Vec<u8> = Vec::new();
+        for action in &actions[1..] {
+            action.print_transaction(
+                &mut output,
+                &Some("Checking".to_string()),
+                symbols.as_ref(),
+            )?;
+        }
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("NDiv\n"));
+        assert!(output.contains("NSell\n"));
+        assert!(!output.contains("L[Checking]"));
+        Ok(())
+    }
+}
@@ -2,27 +2,87 @@ use crate::csv_reader::Reader;
 use crate::find_matching_line::find_matching_line;
 use stable_eyre::eyre::*;
 use std::collections::HashMap;
-use std::io::{BufRead, Seek};
+use std::io::{BufRead, Seek, SeekFrom};
 
 pub struct Readers<'a> {
     readers: HashMap<String, &'a dyn Reader>,
+    content_readers: Vec<&'a dyn Reader>,
 }
 
 impl<'a> Readers<'a> {
     pub fn new() -> Self {
         Readers {
             readers: HashMap::new(),
+            content_readers: Vec::new(),
         }
     }
 
     pub fn register(&mut self, csv_reader: &'a dyn Reader) {
-        self.readers.insert(csv_reader.csv_header(), csv_reader);
+        let csv_header = csv_reader.csv_header();
+        if csv_header.is_empty() {
+            self.content_readers.push(csv_reader);
+        } else {
+            self.readers.insert(csv_header, csv_reader);
+        }
+    }
+
+    // each csv header with the date format of its reader.
+    pub fn csv_headers(&self) -> Vec<(String, String)> {
+        self.readers
+            .iter()
+            .map(|(csv_header, reader)| (csv_header.clone(), reader.date_format()))
+            .collect()
     }
 
+    // csv readers are identified by their header line.   Failing that, the readers that
+    // recognize their input from its contents are asked, with the input rewound to the start.
     pub fn identify_reader<T>(&mut self, buf_reader: &mut T) -> Result<Option<&'a dyn Reader>>
     where
         T: Seek + BufRead,
     {
-        find_matching_line(buf_reader, &self.readers)
+        if let Some(reader) = find_matching_line(buf_reader, &self.readers)? {
+            return Ok(Some(reader));
+        }
+
+        buf_reader.seek(SeekFrom::Start(0))?;
+        let mut contents = Vec::new();
+        buf_reader.read_to_end(&mut contents)?;
+        buf_reader.seek(SeekFrom::Start(0))?;
+
+        Ok(self
+            .content_readers
+            .iter()
+            .find(|reader| reader.recognizes(&contents))
+            .copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schwab_reader::{SchwabJsonReader, SchwabReader};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_identify_reader() -> Result<()> {
+        let mut readers = Readers::new();
+        readers.register(&SchwabReader {});
+        readers.register(&SchwabJsonReader {});
+
+        let mut csv = Cursor::new(SchwabReader {}.csv_header() + "\n");
+        let reader = readers.identify_reader(&mut csv)?.unwrap();
+        assert_eq!(reader.csv_header(), SchwabReader {}.csv_header());
+
+        let mut json = Cursor::new(
+            r#"{"FromDate":"01/01/2024","ToDate":"01/31/2024","BrokerageTransactions":[]}"#,
+        );
+        let reader = readers.identify_reader(&mut json)?.unwrap();
+        assert!(reader.csv_header().is_empty());
+        // the input is rewound for the reader.
+        assert_eq!(json.position(), 0);
+
+        let mut neither = Cursor::new("foo\nbar\n");
+        assert!(readers.identify_reader(&mut neither)?.is_none());
+        Ok(())
     }
 }
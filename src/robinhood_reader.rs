@@ -0,0 +1,368 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{clean_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::option_symbol::{occ_symbol, option_name};
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+pub struct RobinhoodReader;
+
+impl Reader for RobinhoodReader {
+    fn csv_header(&self) -> String {
+        r#""Activity Date","Process Date","Settle Date","Instrument","Description","Trans Code","Quantity","Price","Amount""#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+        let mut transactions: Vec<RobinhoodTransaction> = Vec::new();
+        for record in rdr.deserialize::<RobinhoodTransaction>() {
+            if record.is_err() {
+                // robinhood ends the file with a disclaimer.
+                break;
+            }
+            transactions.push(record?);
+        }
+
+        // an expiration does not say whether the option was held or written, that is told
+        // by the option position, followed from the oldest transaction on.
+        let mut positions: HashMap<String, f64> = HashMap::new();
+        for transaction in transactions.iter_mut().rev() {
+            if let Ok((symbol, _, SecurityType::Option)) = transaction.security_details() {
+                let contracts = transaction.get_quantity()?.abs();
+                let position = positions.entry(symbol).or_insert(0.0);
+                transaction.position = Some(*position);
+                match transaction.trans_code.as_str() {
+                    "BTO" | "BTC" => *position += contracts,
+                    "STO" | "STC" => *position -= contracts,
+                    "OEXP" if *position < 0.0 => *position += contracts,
+                    "OEXP" => *position -= contracts,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        for transaction in transactions.iter().rev() {
+            qif_actions.extend(transaction.to_qif_action(securities)?);
+        }
+        Ok(qif_actions)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RobinhoodTransaction {
+    #[serde(rename = "Activity Date")]
+    pub date: String,
+    #[serde(rename = "Instrument")]
+    pub instrument: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Trans Code")]
+    pub trans_code: String,
+    #[serde(rename = "Quantity")]
+    pub quantity: String,
+    #[serde(rename = "Price")]
+    pub price: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    // option contracts held before this transaction, negative when short.
+    #[serde(skip)]
+    pub position: Option<f64>,
+}
+
+impl RobinhoodTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from robinhood: {}", &self.date))
+    }
+
+    // robinhood descriptions of stocks span lines ("Apple\nCUSIP: 037833100"), keep the first.
+    fn first_description_line(&self) -> String {
+        self.description
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    }
+
+    // Robinhood option descriptions look like "AAPL 1/19/2024 Call $150.00", possibly
+    // preceded by something like "Option Expiration for ".
+    fn get_option(&self) -> Result<(String, String)> {
+        let description_re = Regex::new(
+            r"(?x)
+                               ([A-Z\.]+)                      # underlying symbol
+                               \ (\d{1,2}/\d{1,2}/\d{4})       # expiration date
+                               \ (Call|Put)                    # put or call
+                               \ \$([\d,\.]+)                  # strike price
+                               $",
+        )?;
+
+        let description_cap = description_re
+            .captures(self.description.trim())
+            .ok_or(eyre!("This is not an option!"))?;
+
+        let expiration = NaiveDate::parse_from_str(&description_cap[2], "%m/%d/%Y")?;
+        let put_or_call = if &description_cap[3] == "Call" {
+            "C"
+        } else {
+            "P"
+        };
+        let strike = clean_amount(&description_cap[4]);
+
+        let symbol = occ_symbol(&description_cap[1], &expiration, put_or_call, &strike)?;
+        let name = option_name(
+            put_or_call,
+            &description_cap[1],
+            &description_cap[1],
+            &expiration,
+            &strike,
+        );
+        Ok((symbol, name))
+    }
+
+    fn security_details(&self) -> Result<(String, String, SecurityType)> {
+        match self.get_option() {
+            Ok((symbol, name)) => Ok((symbol, name, SecurityType::Option)),
+            Err(_) => Ok((
+                self.instrument.clone(),
+                self.first_description_line(),
+                SecurityType::Stock,
+            )),
+        }
+    }
+
+    // quantities for share events sometimes carry a trailing S, as in "5S".
+    fn get_quantity(&self) -> Result<f64> {
+        parse_amount(self.quantity.trim().trim_end_matches('S'))
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let (symbol, name, security_type) = self.security_details()?;
+
+        let multiplier = if security_type == SecurityType::Option {
+            100.0
+        } else {
+            1.0
+        };
+        let quantity = format_quantity(self.get_quantity()?.abs() * multiplier);
+        let amount = clean_amount(&self.amount)
+            .trim_start_matches('-')
+            .to_string();
+        let date: NaiveDate = self.get_date()?;
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let res = Trade {
+            date,
+            symbol,
+            price: clean_amount(&self.price),
+            quantity,
+            amount,
+            fees: "".to_string(),
+            memo: None,
+        };
+        Ok(res)
+    }
+
+    // the quantity of an expiration is unsigned : a written option is covered, a held one
+    // is sold, both for nothing.   Options opened before the csv file starts are taken as
+    // held.
+    fn to_expired_action(&self, symbols: &mut Symbols) -> Result<QifAction> {
+        let (symbol, name, security_type) = self.security_details()?;
+        if security_type != SecurityType::Option {
+            return Err(eyre!("OEXP found in CSV for non-option"));
+        }
+
+        let quantity = format_quantity(self.get_quantity()?.abs() * 100.0);
+        let date: NaiveDate = self.get_date()?;
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+        let trade = Trade {
+            date,
+            symbol,
+            price: "".to_string(),
+            quantity,
+            amount: "".to_string(),
+            fees: "".to_string(),
+            memo: None,
+        };
+        match self.position {
+            Some(position) if position < 0.0 => Ok(QifAction::CvrShrt { trade }),
+            Some(position) if position > 0.0 => Ok(QifAction::Sell { trade }),
+            _ => {
+                println!("Expiration of an option opened before the csv file, entered as held:");
+                println!("{:#?}", self);
+                println!();
+                Ok(QifAction::Sell { trade })
+            }
+        }
+    }
+}
+
+impl Transaction for RobinhoodTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        // robinhood ends the file with an empty row and a disclaimer.
+        if self.date.is_empty() {
+            return Ok(res);
+        }
+
+        let csv_code = self.trans_code.as_str();
+        match csv_code {
+            "Buy" | "BTO" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Buy { trade });
+            }
+            "Sell" | "STC" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Sell { trade });
+            }
+            "STO" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::ShtSell { trade });
+            }
+            "BTC" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::CvrShrt { trade });
+            }
+            "OEXP" => {
+                res.push(self.to_expired_action(symbols)?);
+            }
+            "CDIV" => {
+                let (symbol, name, security_type) = self.security_details()?;
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::Div {
+                    date: self.get_date()?,
+                    symbol,
+                    amount: clean_amount(&self.amount),
+                });
+            }
+            "MINT" => {
+                // margin interest is negative but quicken wants it positive.
+                res.push(QifAction::MargInt {
+                    date: self.get_date()?,
+                    memo: self.first_description_line(),
+                    amount: clean_amount(&self.amount)
+                        .trim_start_matches('-')
+                        .to_string(),
+                });
+            }
+            "SLIP" | "GOLD" | "ACH" | "INT" | "DFEE" | "AFEE" | "DTAX" | "XENT" => {
+                res.push(QifAction::Generic {
+                    date: self.get_date()?,
+                    payee: self.first_description_line(),
+                    memo: Some(csv_code.to_string()),
+                    category: None,
+                    amount: clean_amount(&self.amount),
+                });
+            }
+            _ => {
+                if self.quantity.is_empty() && self.price.is_empty() {
+                    println!("Unrecognized trans code found in .CSV : \"{}\".", csv_code);
+
+                    let generic = QifAction::Generic {
+                        date: self.get_date()?,
+                        payee: self.first_description_line(),
+                        memo: Some(csv_code.to_string()),
+                        category: None,
+                        amount: clean_amount(&self.amount),
+                    };
+                    println!("No quantity or price found so entering as cash transaction.");
+                    println!("{:#?}", generic);
+
+                    res.push(generic);
+                } else {
+                    let message =
+                        "Unrecognized trans code found in .CSV file : ".to_string() + csv_code;
+                    return Err(eyre!(message));
+                }
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_robinhood() -> Result<()> {
+        let csv = RobinhoodReader {}.csv_header()
+            + "\n"
+            + r#""1/16/2024","1/16/2024","1/18/2024","AAPL","AAPL 1/19/2024 Call $150.00","STO","1","$2.50","$249.95""#
+            + "\n"
+            + "\"1/15/2024\",\"1/15/2024\",\"1/17/2024\",\"AAPL\",\"Apple\nCUSIP: 037833100\",\"Buy\",\"10\",\"$150.00\",\"($1,500.00)\"\n"
+            + "\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\"\n"
+            + "\"The data provided is for informational purposes only.\"\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = RobinhoodReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 2);
+
+        // csv order is reversed.
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.symbol, "AAPL");
+                assert_eq!(trade.price, "150.00");
+                assert_eq!(trade.amount, "1500.00");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::ShtSell { trade } => {
+                assert_eq!(trade.symbol, "AAPL  240119C00150000");
+                assert_eq!(trade.quantity, "100");
+                assert_eq!(trade.amount, "249.95");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_expirations() -> Result<()> {
+        let csv = RobinhoodReader {}.csv_header()
+            + "\n"
+            + r#""1/19/2024","1/19/2024","1/19/2024","AAPL","Option Expiration for AAPL 1/19/2024 Put $140.00","OEXP","2","","""#
+            + "\n"
+            + r#""1/19/2024","1/19/2024","1/19/2024","AAPL","Option Expiration for AAPL 1/19/2024 Call $150.00","OEXP","1","","""#
+            + "\n"
+            + r#""1/16/2024","1/16/2024","1/17/2024","AAPL","AAPL 1/19/2024 Put $140.00","STO","2","$1.00","$199.90""#
+            + "\n"
+            + r#""1/15/2024","1/15/2024","1/16/2024","AAPL","AAPL 1/19/2024 Call $150.00","BTO","1","$2.50","($250.00)""#
+            + "\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = RobinhoodReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        match &actions[..] {
+            [QifAction::Buy { .. }, QifAction::ShtSell { .. }, QifAction::Sell { trade: call }, QifAction::CvrShrt { trade: put }] =>
+            {
+                assert_eq!(call.symbol, "AAPL  240119C00150000");
+                assert_eq!(call.quantity, "100");
+                assert_eq!(put.symbol, "AAPL  240119P00140000");
+                assert_eq!(put.quantity, "200");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+use chrono::NaiveDate;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{clean_amount, format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// Schwab Equity Award Center export.
+//
+// Each transaction row may be followed by detail rows, one per lot, which leave the
+// transaction columns empty.   The detail rows are preceded by their own header row naming
+// columns such as "Award Date", "FMV" or "Shares Sold".   The rows have differing numbers of
+// columns, so this does not go through <dyn Reader>::from_csv.
+pub struct SchwabEacReader;
+
+impl Reader for SchwabEacReader {
+    fn csv_header(&self) -> String {
+        r#""Date","Action","Symbol","Description","Quantity","Fees & Comm","Disbursement Election","Amount""#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let symbols = securities
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+
+        let awards = EacTransaction::read_all(bufreader)?;
+        let mut lapses: Vec<(&str, NaiveDate, f64)> = Vec::new();
+        for award in awards.iter().filter(|award| award.action == "Lapse") {
+            lapses.push((&award.symbol, award.get_date()?, award.net_shares()?));
+        }
+
+        let mut qif_actions: Vec<Vec<QifAction>> = Vec::new();
+        for award in &awards {
+            // the net shares of a lapse are deposited within a few days, already entered
+            // with the lapse.   Other deposits, such as ESPP purchases, bring shares of their
+            // own.
+            if award.action == "Deposit" && !award.is_purchase() {
+                let date = award.get_date()?;
+                let quantity = parse_amount(&award.quantity)?;
+                if lapses.iter().any(|(symbol, lapse_date, net_shares)| {
+                    *symbol == award.symbol
+                        && *lapse_date <= date
+                        && (date - *lapse_date).num_days() <= 7
+                        && (*net_shares - quantity).abs() < 0.0001
+                }) {
+                    continue;
+                }
+            }
+            qif_actions.push(award.to_qif_action(symbols)?);
+        }
+
+        // reversing because csv files typically have newest transactions first.
+        Ok(qif_actions.into_iter().rev().flatten().collect())
+    }
+}
+
+// lower case and without spaces or punctuation so that "Award Date" matches "AwardDate".
+fn normalize_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+const DETAIL_LABELS: &[&str] = &[
+    "awarddate",
+    "awardid",
+    "fmv",
+    "fairmarketvalue",
+    "fairmarketvalueprice",
+    "saleprice",
+    "shares",
+    "sharessold",
+    "sharessoldwithheldfortaxes",
+    "netsharesdeposited",
+    "taxes",
+    "type",
+    "grantid",
+    "vestdate",
+    "vestfairmarketvalue",
+    "vestfmv",
+    "purchasedate",
+    "purchaseprice",
+    "purchasefairmarketvalue",
+    "subscriptiondate",
+    "subscriptionfairmarketvalue",
+    "dispositiontype",
+    "grossproceeds",
+];
+
+#[derive(Debug, Clone)]
+pub struct EacTransaction {
+    pub date: String,
+    pub action: String,
+    pub symbol: String,
+    pub description: String,
+    pub quantity: String,
+    pub fees: String,
+    pub amount: String,
+    // one map per lot, keyed by normalized detail label.
+    pub details: Vec<HashMap<String, String>>,
+}
+
+impl EacTransaction {
+    fn read_all(bufreader: &mut dyn BufRead) -> Result<Vec<EacTransaction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+
+        let mut transactions: Vec<EacTransaction> = Vec::new();
+        let mut detail_labels: Vec<String> = Vec::new();
+        for result in rdr.records() {
+            let record = match result {
+                Ok(record) => record,
+                // some csv files are not too clean.
+                Err(_) => break,
+            };
+            let field = |i: usize| record.get(i).unwrap_or("").trim().to_string();
+
+            if !field(0).is_empty() {
+                if record.len() < 8 {
+                    break;
+                }
+                transactions.push(EacTransaction {
+                    date: field(0),
+                    action: field(1),
+                    symbol: field(2),
+                    description: field(3),
+                    quantity: field(4),
+                    fees: field(5),
+                    amount: field(7),
+                    details: Vec::new(),
+                });
+                continue;
+            }
+
+            let normalized: Vec<String> = record.iter().map(normalize_label).collect();
+            if normalized
+                .iter()
+                .any(|label| DETAIL_LABELS.contains(&label.as_str()))
+            {
+                detail_labels = normalized;
+                continue;
+            }
+
+            let transaction = match transactions.last_mut() {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+            let mut detail = HashMap::new();
+            for (label, value) in detail_labels.iter().zip(record.iter()) {
+                if !label.is_empty() && !value.trim().is_empty() {
+                    detail.insert(label.clone(), value.trim().to_string());
+                }
+            }
+            if !detail.is_empty() {
+                transaction.details.push(detail);
+            }
+        }
+        Ok(transactions)
+    }
+
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from schwab: {}", &self.date))
+    }
+
+    fn detail<'a>(detail: &'a HashMap<String, String>, labels: &[&str]) -> Option<&'a String> {
+        labels.iter().find_map(|label| detail.get(*label))
+    }
+
+    // per share fair market value of a lot, used as its cost basis.
+    fn lot_price(detail: &HashMap<String, String>) -> Option<String> {
+        Self::detail(
+            detail,
+            &[
+                "fairmarketvalueprice",
+                "fmv",
+                "fairmarketvalue",
+                "vestfairmarketvalue",
+                "vestfmv",
+                "purchasefairmarketvalue",
+                "purchaseprice",
+            ],
+        )
+        .map(|price| clean_amount(price))
+    }
+
+    // the shares of a lapse left after those withheld for taxes.
+    fn net_shares(&self) -> Result<f64> {
+        let mut net_shares = 0.0;
+        let mut found = false;
+        for detail in &self.details {
+            if let Some(quantity) = Self::detail(detail, &["netsharesdeposited"]) {
+                net_shares += parse_amount(quantity)?;
+                found = true;
+            }
+        }
+        if found {
+            Ok(net_shares)
+        } else {
+            parse_amount(&self.quantity)
+        }
+    }
+
+    // ESPP deposits come with the details of their purchase.
+    fn is_purchase(&self) -> bool {
+        self.details
+            .iter()
+            .any(|detail| Self::detail(detail, &["purchasedate", "purchaseprice"]).is_some())
+    }
+
+    fn lot_quantity(&self, detail: &HashMap<String, String>) -> String {
+        Self::detail(detail, &["netsharesdeposited", "shares"])
+            .map(|quantity| clean_amount(quantity))
+            .unwrap_or_else(|| clean_amount(&self.quantity))
+    }
+
+    // one ShrsIn per lot, each with the lot's fair market value as cost basis.
+    fn to_shares_in(&self, symbol: &str) -> Result<Vec<QifAction>> {
+        let date = self.get_date()?;
+        let mut res = Vec::new();
+        if self.details.is_empty() {
+            println!(
+                "No lot details found for {} of {} on {}, entering without cost basis.",
+                self.action, self.symbol, self.date
+            );
+            res.push(QifAction::ShrsIn {
+                date,
+                symbol: symbol.to_string(),
+                quantity: clean_amount(&self.quantity),
+                price: None,
+                amount: None,
+            });
+            return Ok(res);
+        }
+
+        // with a single lot the transaction quantity is the lot quantity.
+        let single_lot = self.details.len() == 1;
+        for detail in &self.details {
+            let quantity = if single_lot && Self::detail(detail, &["netsharesdeposited"]).is_none()
+            {
+                clean_amount(&self.quantity)
+            } else {
+                self.lot_quantity(detail)
+            };
+            let price = Self::lot_price(detail);
+            let amount = match &price {
+                Some(price) => Some(format_amount(
+                    parse_amount(&quantity)? * parse_amount(price)?,
+                )),
+                None => None,
+            };
+            res.push(QifAction::ShrsIn {
+                date,
+                symbol: symbol.to_string(),
+                quantity,
+                price,
+                amount,
+            });
+        }
+        Ok(res)
+    }
+
+    fn to_sale(&self, symbol: &str) -> Result<QifAction> {
+        let price = self
+            .details
+            .iter()
+            .find_map(|detail| Self::detail(detail, &["saleprice"]))
+            .map(|price| clean_amount(price))
+            .unwrap_or_default();
+        let trade = Trade {
+            date: self.get_date()?,
+            symbol: symbol.to_string(),
+            price,
+            quantity: format_quantity(parse_amount(&self.quantity)?.abs()),
+            amount: clean_amount(&self.amount),
+            fees: clean_amount(&self.fees),
+            memo: None,
+        };
+        Ok(QifAction::Sell { trade })
+    }
+
+    fn to_qif_action(&self, symbols: &mut Symbols) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let symbol = self.symbol.clone();
+        if !symbol.is_empty() {
+            symbols.enter_if_not_found(&symbol, &symbol, &SecurityType::Stock)?;
+        }
+
+        let csv_action = self.action.as_str();
+        match csv_action {
+            "Lapse" | "Deposit" => {
+                res.append(&mut self.to_shares_in(&symbol)?);
+            }
+            "Sale" | "Quick Sale" | "Forced Quick Sell" => {
+                res.push(self.to_sale(&symbol)?);
+            }
+            _ => {
+                if self.quantity.is_empty() && !self.amount.is_empty() {
+                    res.push(QifAction::Generic {
+                        date: self.get_date()?,
+                        payee: self.description.clone(),
+                        memo: Some(self.action.clone()),
+                        category: None,
+                        amount: clean_amount(&self.amount),
+                    });
+                } else {
+                    println!("Equity award action not handled : \"{}\".", csv_action);
+                    println!("{:#?}", self);
+                    println!();
+                }
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_lapse_and_sale() -> Result<()> {
+        let csv = SchwabEacReader {}.csv_header()
+            + "\n"
+            + r#""02/01/2024","Sale","XYZ","Share Sale","10","$0.12","","$1,499.88""#
+            + "\n"
+            + r#""","Type","Shares","Sale Price","Grant Id","Vest Date","Vest FMV""#
+            + "\n"
+            + r#""","RS","10","$150.00","G1","01/15/2024","$140.00""#
+            + "\n"
+            + r#""01/15/2024","Lapse","XYZ","Restricted Stock Lapse","100","","","""#
+            + "\n"
+            + r#""","Award Date","Award ID","FMV","Sale Price","Shares Sold","Net Shares Deposited","Taxes""#
+            + "\n"
+            + r#""","01/01/2022","G1","$140.00","","","40","""#
+            + "\n"
+            + r#""","01/01/2023","G2","$140.00","","","22","""#
+            + "\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = SchwabEacReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 3);
+
+        match &actions[0] {
+            QifAction::ShrsIn {
+                quantity,
+                price,
+                amount,
+                ..
+            } => {
+                assert_eq!(quantity, "40");
+                assert_eq!(price.as_deref(), Some("140.00"));
+                assert_eq!(amount.as_deref(), Some("5600.00"));
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::ShrsIn { quantity, .. } => assert_eq!(quantity, "22"),
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[2] {
+            QifAction::Sell { trade } => {
+                assert_eq!(trade.price, "150.00");
+                assert_eq!(trade.amount, "1499.88");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_lapse_and_deposit() -> Result<()> {
+        let csv = SchwabEacReader {}.csv_header()
+            + "\n"
+            + r#""03/01/2024","Deposit","XYZ","ESPP","15","","","""#
+            + "\n"
+            + r#""","Purchase Date","Purchase Price","Subscription Date","Subscription Fair Market Value","Purchase Fair Market Value""#
+            + "\n"
+            + r#""","02/29/2024","$120.00","09/01/2023","$130.00","$145.00""#
+            + "\n"
+            + r#""01/18/2024","Deposit","XYZ","ESPP","15","","","""#
+            + "\n"
+            + r#""","Purchase Date","Purchase Price","Subscription Date","Subscription Fair Market Value","Purchase Fair Market Value""#
+            + "\n"
+            + r#""","01/16/2024","$110.00","07/01/2023","$130.00","$138.00""#
+            + "\n"
+            + r#""01/17/2024","Deposit","XYZ","RS","62","","","""#
+            + "\n"
+            + r#""01/15/2024","Lapse","XYZ","Restricted Stock Lapse","100","","","""#
+            + "\n"
+            + r#""","Award Date","Award ID","FMV","Sale Price","Shares Sold","Net Shares Deposited","Taxes""#
+            + "\n"
+            + r#""","01/01/2022","G1","$140.00","","","62","""#
+            + "\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = SchwabEacReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+
+        // the deposit of the lapsed shares is not entered a second time, the ESPP
+        // purchase deposited in the same week is.
+        match &actions[..] {
+            [QifAction::ShrsIn {
+                quantity: lapse, ..
+            }, QifAction::ShrsIn {
+                quantity: espp_in_vest_week,
+                price: vest_week_price,
+                ..
+            }, QifAction::ShrsIn {
+                quantity: espp,
+                price,
+                ..
+            }] => {
+                assert_eq!(espp_in_vest_week, "15");
+                assert_eq!(vest_week_price.as_deref(), Some("138.00"));
+                assert_eq!(lapse, "62");
+                assert_eq!(espp, "15");
+                assert_eq!(price.as_deref(), Some("145.00"));
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use std::io::BufRead;
 use std::result::Result::Ok;
 
 use crate::csv_reader::*;
+use crate::option_symbol::{occ_symbol, option_name};
 use crate::security::SecurityType;
 use crate::symbols::Symbols;
 use crate::transaction::*;
@@ -46,6 +47,51 @@ impl Reader for SchwabReaderOldCsv {
     }
 }
 
+// Schwab's history page can also export json.   The transactions have the same fields as
+// the csv export and go through the same SchwabTransaction::to_qif_action.
+pub struct SchwabJsonReader;
+
+#[derive(Debug, Deserialize)]
+pub struct SchwabJson {
+    #[serde(rename = "FromDate")]
+    pub from_date: String,
+    #[serde(rename = "ToDate")]
+    pub to_date: String,
+    #[serde(rename = "BrokerageTransactions")]
+    pub brokerage_transactions: Vec<SchwabTransaction>,
+}
+
+impl Reader for SchwabJsonReader {
+    fn csv_header(&self) -> String {
+        String::new()
+    }
+
+    fn recognizes(&self, contents: &[u8]) -> bool {
+        serde_json::from_slice::<SchwabJson>(contents).is_ok()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let schwab_json: SchwabJson = serde_json::from_reader(bufreader)
+            .context("Unable to parse schwab json transactions")?;
+        println!(
+            "Reading schwab transactions from {} to {}.",
+            schwab_json.from_date, schwab_json.to_date
+        );
+
+        let mut qif_actions: Vec<Vec<QifAction>> = Vec::new();
+        for transaction in schwab_json.brokerage_transactions {
+            qif_actions.push(transaction.to_qif_action(securities)?);
+        }
+
+        // reversing because schwab lists newest transactions first.
+        Ok(qif_actions.into_iter().rev().flatten().collect())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct SchwabTransaction {
     #[serde(rename = "Date")]
@@ -89,12 +135,12 @@ impl SchwabTransaction {
                         Err(_) => {
                             let err_msg = "Could not parse date from schwab on second try: "
                                 .to_string()
-                                + &self.date;
+                                + self.date.as_str();
                             return Err(eyre!(err_msg));
                         }
                     }
                 }
-                let err_msg = "Could not match date from schwab: ".to_string() + &self.date;
+                let err_msg = "Could not match date from schwab: ".to_string() + self.date.as_str();
                 Err(eyre!(err_msg))
             }
         }
@@ -199,13 +245,15 @@ impl Transaction for SchwabTransaction {
 
             "Spin-off" => {
                 let (symbol, name, security_type) = cleaned_record.security_details()?;
-                let quantity = cleaned_record.quantity.parse::<i32>()?;
+                let quantity = cleaned_record.quantity.clone();
                 let date: NaiveDate = cleaned_record.get_date()?;
                 symbols.enter_if_not_found(&symbol, &name, &security_type)?;
                 res.push(QifAction::ShrsIn {
                     date,
                     symbol,
                     quantity,
+                    price: None,
+                    amount: None,
                 });
             }
 
@@ -299,48 +347,15 @@ impl SchwabTransaction {
                 let symbol_is_call = symbol_cap[4].eq("C");
                 assert_eq!(description_is_call, symbol_is_call);
 
-                let strike_re = Regex::new(
-                    r"(?x)^
-                                       ([\d]*)                 # dollars
-                                       \.
-                                       ([\d]*)                 # cents
-                                       $",
-                )?;
-
-                let mut matched = false;
-                let mut strike_string = String::new();
-                for strike_cap in strike_re.captures_iter(strike_price) {
-                    if matched {
-                        return Err(eyre!("got multiple matches on strike"));
-                    }
-                    matched = true;
-                    let dollars = &strike_cap[1];
-                    let cents = &strike_cap[2];
-                    strike_string =
-                        format!("{:0>5}", dollars).to_string() + &format!("{:0<3}", cents);
-                }
-                if !matched {
-                    return Err(eyre!("got no matches on strike"));
-                }
+                let symbol = occ_symbol(&symbol_cap[1], &expiration, &symbol_cap[4], strike_price)?;
 
-                let padded_symbol = format!("{: <6}", &symbol_cap[1]);
-
-                let symbol = padded_symbol
-                    + &expiration.format("%y%m%d").to_string()
-                    + &symbol_cap[4]
-                    + &strike_string;
-
-                let name = description_cap[1].to_string()
-                    + " : "
-                    + description_cap[2].trim_end()
-                    + " - "
-                    + &symbol_cap[1]
-                    + " "
-                    + &expiration.format("%m/%d/%Y").to_string()
-                    + " "
-                    + strike_price
-                    + " "
-                    + &symbol_cap[4];
+                let name = option_name(
+                    &symbol_cap[4],
+                    &description_cap[2],
+                    &symbol_cap[1],
+                    &expiration,
+                    strike_price,
+                );
 
                 return Ok((symbol, name));
             }
@@ -392,6 +407,7 @@ impl SchwabTransaction {
             quantity,
             amount,
             fees,
+            memo: None,
         };
         Ok(res)
     }
@@ -414,7 +430,7 @@ impl SchwabTransaction {
         let q = if leading == "-" {
             rest.to_string()
         } else {
-            "-".to_string() + &schwab_transaction.quantity
+            "-".to_string() + schwab_transaction.quantity.as_str()
         };
 
         let quantity = q + "00";
@@ -427,7 +443,56 @@ impl SchwabTransaction {
             quantity,
             amount,
             fees,
+            memo: None,
         };
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn to_qif(reader: &dyn Reader, input: String) -> Result<String> {
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = reader.to_transactions(&mut Cursor::new(input), &mut symbols)?;
+        assert_eq!(actions.len(), 3);
+        let mut output: Vec<u8> = Vec::new();
+        for action in &actions {
+            action.print_transaction(&mut output, &Some("Cash".to_string()), symbols.as_ref())?;
+        }
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn test_schwab_csv_and_json() -> Result<()> {
+        let csv = SchwabReader {}.csv_header()
+            + "\n"
+            + r#""03/15/2024","Cash Dividend","AAPL","APPLE INC","","","","$2.40""#
+            + "\n"
+            + r#""02/01/2024","Sell","AAPL","APPLE INC","$190.00","5","$0.02","$949.98""#
+            + "\n"
+            + r#""01/16/2024 as of 01/15/2024","Buy","AAPL","APPLE INC","$185.00","10","","-$1,850.00""#
+            + "\n";
+        let json = r#"{
+            "FromDate": "01/01/2024",
+            "ToDate": "03/31/2024",
+            "BrokerageTransactions": [
+                {"Date": "03/15/2024", "Action": "Cash Dividend", "Symbol": "AAPL", "Description": "APPLE INC", "Quantity": "", "Price": "", "Fees & Comm": "", "Amount": "$2.40"},
+                {"Date": "02/01/2024", "Action": "Sell", "Symbol": "AAPL", "Description": "APPLE INC", "Quantity": "5", "Price": "$190.00", "Fees & Comm": "$0.02", "Amount": "$949.98"},
+                {"Date": "01/16/2024 as of 01/15/2024", "Action": "Buy", "Symbol": "AAPL", "Description": "APPLE INC", "Quantity": "10", "Price": "$185.00", "Fees & Comm": "", "Amount": "-$1,850.00"}
+            ]
+        }"#;
+        assert!(SchwabJsonReader {}.recognizes(json.as_bytes()));
+
+        let from_csv = to_qif(&SchwabReader {}, csv)?;
+        let from_json = to_qif(&SchwabJsonReader {}, json.to_string())?;
+        assert_eq!(from_csv, from_json);
+        assert!(from_csv.starts_with("D1/15'24\nNBuyX\nYApple Inc.\n"));
+        assert!(from_csv.contains("NSellX\n"));
+        assert!(from_csv.contains("NDivX\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use chrono::NaiveDate;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, ParseError, Scope, AST};
+use stable_eyre::eyre::*;
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::result::Result::Ok;
+
+use crate::action_builder::{build_actions, ActionFields};
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// A reader written as a rhai script, for files too irregular for a toml profile.   Every
+// *.rhai file in the directory given with --scripts is a script.
+//
+// A script defines :
+//
+//     fn header() { "Date,Time,Description,Amount" }
+//
+//     fn convert(record) {
+//         #{ kind: "Generic", date: record.Date, payee: record.Description,
+//            amount: record.Amount }
+//     }
+//
+// and optionally finish(), called after the last record.   convert gets each csv record as
+// a map keyed by column name, and convert and finish return an action map, an array of
+// them, or () for none.   Action maps have the fields of ActionFields, with the date as
+// yyyy-mm-dd unless a date_format is given.   The kind defaults to "Generic".
+//
+// convert and finish are called with `this` bound to a map kept from one record to the
+// next, for records that only make sense together.   The dates of a spreadsheet are
+// given to scripts as mm/dd/yyyy.
+pub struct ScriptReader {
+    pub path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    header: String,
+}
+
+// rhai reports errors in functions called by a script wrapped in an error for the call,
+// the innermost one has the line that went wrong.
+fn innermost(err: EvalAltResult) -> EvalAltResult {
+    match err {
+        EvalAltResult::ErrorInFunctionCall(_, _, inner, _)
+        | EvalAltResult::ErrorInModule(_, inner, _) => innermost(*inner),
+        other => other,
+    }
+}
+
+fn script_error(path: &Path, err: EvalAltResult) -> Report {
+    let mut err = innermost(err);
+    let position = err.take_position();
+    match position.line() {
+        Some(line) => eyre!("Error in script {:?}, line {} : {}", path, line, err),
+        None => eyre!("Error in script {:?} : {}", path, err),
+    }
+}
+
+fn parse_error(path: &Path, err: ParseError) -> Report {
+    match err.position().line() {
+        Some(line) => eyre!(
+            "Error in script {:?}, line {} : {}",
+            path,
+            line,
+            err.err_type()
+        ),
+        None => eyre!("Error in script {:?} : {}", path, err.err_type()),
+    }
+}
+
+impl ScriptReader {
+    pub fn from_file(path: &Path) -> Result<ScriptReader> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read script : {:#?}", path))?;
+        ScriptReader::from_source(path, &contents)
+    }
+
+    pub fn from_source(path: &Path, contents: &str) -> Result<ScriptReader> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile(contents)
+            .map_err(|err| parse_error(path, err))?;
+        let header = engine
+            .call_fn::<String>(&mut Scope::new(), &ast, "header", ())
+            .map_err(|err| script_error(path, *err))?;
+        Ok(ScriptReader {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            header,
+        })
+    }
+
+    // every *.rhai file in the directory, in file name order.
+    pub fn load_dir(dir: &Path) -> Result<Vec<ScriptReader>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("Unable to read scripts directory : {:#?}", dir))?
+        {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "rhai")
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| ScriptReader::from_file(path))
+            .collect()
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    fn call(&self, name: &str, state: &mut Dynamic, args: Vec<Dynamic>) -> Result<Dynamic> {
+        let options = rhai::CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(state);
+        let result = match args.into_iter().next() {
+            Some(arg) => self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                name,
+                (arg,),
+            ),
+            None => self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                name,
+                (),
+            ),
+        };
+        result.map_err(|err| script_error(&self.path, *err))
+    }
+
+    fn to_fields(&self, action: Map) -> Result<(String, ActionFields)> {
+        let get = |key: &str| -> String {
+            action
+                .get(key)
+                .filter(|value| !value.is_unit())
+                .map(|value| value.to_string())
+                .unwrap_or_default()
+        };
+        let non_empty = |value: String| if value.is_empty() { None } else { Some(value) };
+
+        let kind = non_empty(get("kind")).unwrap_or_else(|| "Generic".to_string());
+        let date_format = non_empty(get("date_format")).unwrap_or_else(|| "%Y-%m-%d".to_string());
+        let date = NaiveDate::parse_from_str(&get("date"), &date_format).with_context(|| {
+            format!(
+                "Could not parse date returned by script {:?} with format {} : {}",
+                self.path,
+                date_format,
+                get("date")
+            )
+        })?;
+        let fields = ActionFields {
+            date,
+            payee: get("payee"),
+            memo: non_empty(get("memo")),
+            category: non_empty(get("category")),
+            amount: get("amount"),
+            symbol: get("symbol"),
+            security_name: non_empty(get("security_name")),
+            quantity: get("quantity"),
+            price: get("price"),
+            fees: get("fees"),
+        };
+        Ok((kind, fields))
+    }
+
+    fn to_qif_actions(
+        &self,
+        result: Dynamic,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let maps: Vec<Dynamic> = if result.is_unit() {
+            Vec::new()
+        } else if result.is_array() {
+            result.cast::<Array>()
+        } else {
+            vec![result]
+        };
+
+        let mut res: Vec<QifAction> = Vec::new();
+        for map in maps {
+            let type_name = map.type_name();
+            let map = map.try_cast::<Map>().ok_or(eyre!(
+                "Script {:?} returned {} instead of an action map",
+                self.path,
+                type_name
+            ))?;
+            let (kind, fields) = self.to_fields(map)?;
+            res.append(&mut build_actions(&kind, &fields, securities)?);
+        }
+        Ok(res)
+    }
+}
+
+impl Reader for ScriptReader {
+    fn csv_header(&self) -> String {
+        self.header.clone()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+        let headers = rdr.headers()?.clone();
+
+        let mut state = Dynamic::from_map(Map::new());
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        for result in rdr.records() {
+            let record = match result {
+                Ok(record) => record,
+                // some csv files are not too clean.
+                Err(_) => break,
+            };
+            let mut row = Map::new();
+            for (column, value) in headers.iter().zip(record.iter()) {
+                row.insert(column.into(), value.trim().into());
+            }
+            let result = self.call("convert", &mut state, vec![Dynamic::from_map(row)])?;
+            qif_actions.append(&mut self.to_qif_actions(result, securities)?);
+        }
+        if self.has_fn("finish") {
+            let result = self.call("finish", &mut state, Vec::new())?;
+            qif_actions.append(&mut self.to_qif_actions(result, securities)?);
+        }
+
+        // the script returns transactions in file order, sort so that newest first files work.
+        qif_actions.sort_by_key(QifAction::date);
+        Ok(qif_actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_script() -> Result<()> {
+        let reader = ScriptReader::from_file(&PathBuf::from("test_data/scripts/split_rows.rhai"))?;
+        let csv = reader.csv_header()
+            + "\n"
+            + "2024-01-15 10:30,Grocer,\n"
+            + ",,-12.34\n"
+            + "2024-01-16 09:00,Payroll,\n"
+            + ",,1000.00\n";
+        let actions = reader.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 2);
+        match &actions[0] {
+            QifAction::Generic { payee, amount, .. } => {
+                assert_eq!(payee, "Grocer");
+                assert_eq!(amount, "-12.34");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_errors() {
+        let path = PathBuf::from("bad.rhai");
+        let err = ScriptReader::from_source(&path, "fn header() { \"A,B\" }\n\nlet x = ;\n")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("\"bad.rhai\", line 3"), "{}", err);
+
+        let reader = ScriptReader::from_source(
+            &path,
+            "fn header() { \"A,B\" }\n\nfn convert(record) {\n    record.A.no_such_fn()\n}\n",
+        )
+        .unwrap();
+        let err = reader
+            .to_transactions(&mut Cursor::new("A,B\n1,2\n"), &mut None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("\"bad.rhai\", line 4"), "{}", err);
+    }
+}
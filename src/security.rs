@@ -1,7 +1,28 @@
+use stable_eyre::eyre::*;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SecurityType {
     Option,
     Stock,
     MutualFund,
     MarketIndex,
+    Future,
+    Crypto,
+    Bond,
+}
+
+impl SecurityType {
+    // the type as named in a quicken securities list.
+    pub fn from_qif(security_type: &str) -> Result<SecurityType> {
+        match security_type {
+            "Option" => Ok(SecurityType::Option),
+            "Stock" => Ok(SecurityType::Stock),
+            "Mutual Fund" => Ok(SecurityType::MutualFund),
+            "Market Index" => Ok(SecurityType::MarketIndex),
+            "Future" => Ok(SecurityType::Future),
+            "Cryptocurrency" => Ok(SecurityType::Crypto),
+            "Bond" => Ok(SecurityType::Bond),
+            _ => Err(eyre!("unrecognized security type: {}", security_type)),
+        }
+    }
 }
@@ -4,7 +4,9 @@ use stable_eyre::eyre::*;
 use std::io::BufRead;
 use std::result::Result::Ok;
 
+use crate::amounts::clean_amount;
 use crate::csv_reader::*;
+use crate::security::SecurityType;
 use crate::symbols::Symbols;
 use crate::transaction::*;
 use crate::transactions_qif::*;
@@ -16,6 +18,10 @@ impl Reader for SoFiReader {
         r#"Date,Description,Type,Amount,Current balance,Status"#.to_string()
     }
 
+    fn date_format(&self) -> String {
+        "%Y-%m-%d".to_string()
+    }
+
     fn to_transactions(
         &self,
         bufreader: &mut dyn BufRead,
@@ -25,6 +31,7 @@ impl Reader for SoFiReader {
     }
 }
 
+#[allow(dead_code)] // every column is deserialized even though not all are used.
 #[derive(Debug, Clone, Deserialize)]
 pub struct SoFiTransaction {
     #[serde(rename = "Date")]
@@ -71,3 +78,180 @@ impl Transaction for SoFiTransaction {
         Ok(res)
     }
 }
+
+// SoFi Invest brokerage activity.
+pub struct SoFiInvestReader;
+
+impl Reader for SoFiInvestReader {
+    fn csv_header(&self) -> String {
+        r#"Date,Activity,Symbol,Description,Quantity,Price,Amount"#.to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<SoFiInvestTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SoFiInvestTransaction {
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Activity")]
+    pub activity: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Quantity")]
+    pub quantity: String,
+    #[serde(rename = "Price")]
+    pub price: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+}
+
+impl SoFiInvestTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from sofi: {}", &self.date))
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let name = if self.description.is_empty() {
+            self.symbol.clone()
+        } else {
+            self.description.clone()
+        };
+        symbols.enter_if_not_found(&self.symbol, &name, &SecurityType::Stock)?;
+        let res = Trade {
+            date: self.get_date()?,
+            symbol: self.symbol.clone(),
+            price: clean_amount(&self.price),
+            quantity: clean_amount(&self.quantity)
+                .trim_start_matches('-')
+                .to_string(),
+            amount: clean_amount(&self.amount)
+                .trim_start_matches('-')
+                .to_string(),
+            fees: "".to_string(),
+            memo: None,
+        };
+        Ok(res)
+    }
+}
+
+impl Transaction for SoFiInvestTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let csv_activity = self.activity.as_str();
+        match csv_activity {
+            "Buy" | "Dividend Reinvestment" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Buy { trade });
+            }
+            "Sell" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Sell { trade });
+            }
+            "Dividend" | "Cash Dividend" => {
+                symbols.enter_if_not_found(
+                    &self.symbol,
+                    &self.description,
+                    &SecurityType::Stock,
+                )?;
+                res.push(QifAction::Div {
+                    date: self.get_date()?,
+                    symbol: self.symbol.clone(),
+                    amount: clean_amount(&self.amount),
+                });
+            }
+            "Deposit" | "Withdrawal" | "Interest" | "Fee" => {
+                res.push(QifAction::Generic {
+                    date: self.get_date()?,
+                    payee: self.description.clone(),
+                    memo: Some(csv_activity.to_string()),
+                    category: None,
+                    amount: clean_amount(&self.amount),
+                });
+            }
+            _ => {
+                if self.quantity.is_empty() && self.price.is_empty() {
+                    println!(
+                        "Unrecognized activity found in .CSV : \"{}\".",
+                        csv_activity
+                    );
+
+                    let generic = QifAction::Generic {
+                        date: self.get_date()?,
+                        payee: self.description.clone(),
+                        memo: Some(csv_activity.to_string()),
+                        category: None,
+                        amount: clean_amount(&self.amount),
+                    };
+                    println!("No quantity or price found so entering as cash transaction.");
+                    println!("{:#?}", generic);
+
+                    res.push(generic);
+                } else {
+                    let message =
+                        "Unrecognized activity found in .CSV file : ".to_string() + csv_activity;
+                    return Err(eyre!(message));
+                }
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sofi_invest() -> Result<()> {
+        let csv = SoFiInvestReader {}.csv_header()
+            + "\n"
+            + "2024-01-17,Dividend,VTI,Vanguard Total Stock Market ETF,,,$1.23\n"
+            + "2024-01-16,Sell,AAPL,Apple Inc.,-2,$155.00,$310.00\n"
+            + "2024-01-15,Buy,VTI,Vanguard Total Stock Market ETF,0.123456,$230.00,-$28.39\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = SoFiInvestReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 3);
+
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.symbol, "VTI");
+                assert_eq!(trade.quantity, "0.123456");
+                assert_eq!(trade.amount, "28.39");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[1] {
+            QifAction::Sell { trade } => assert_eq!(trade.quantity, "2"),
+            other => panic!("unexpected action: {:?}", other),
+        }
+        match &actions[2] {
+            QifAction::Div { amount, .. } => assert_eq!(amount, "1.23"),
+            other => panic!("unexpected action: {:?}", other),
+        }
+        assert_eq!(
+            symbols.unwrap().lookup(&"VTI".to_string())?,
+            "Vanguard Total Stock Market ETF"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::NaiveDate;
+use stable_eyre::eyre::*;
+
+use crate::amounts::{format_amount, parse_amount};
+use crate::transactions_qif::*;
+
+// Several banks (Capital One, Citi, many credit unions) export separate Debit and Credit
+// columns instead of one signed amount.   Their transactions implement this trait and
+// call to_generic_action from their Transaction::to_qif_action.
+pub trait SplitAmountTransaction {
+    fn get_date(&self) -> Result<NaiveDate>;
+    fn payee(&self) -> String;
+    fn debit(&self) -> &str;
+    fn credit(&self) -> &str;
+
+    fn memo(&self) -> Option<String> {
+        None
+    }
+
+    fn category(&self) -> Option<String> {
+        None
+    }
+}
+
+// Debits become negative and credits positive amounts.   Some banks already write one or
+// the other with a sign, so the sign in the csv is ignored.
+pub fn signed_amount(debit: &str, credit: &str) -> Result<String> {
+    let debit = debit.trim();
+    let credit = credit.trim();
+    match (debit.is_empty(), credit.is_empty()) {
+        (false, true) => Ok(format_amount(-parse_amount(debit)?.abs())),
+        (true, false) => Ok(format_amount(parse_amount(credit)?.abs())),
+        (false, false) => Ok(format_amount(
+            parse_amount(credit)?.abs() - parse_amount(debit)?.abs(),
+        )),
+        (true, true) => Err(eyre!("Neither debit nor credit amount found")),
+    }
+}
+
+// rows without any amount (pending or zero amount transactions) are skipped.
+pub fn to_generic_action<T>(transaction: &T) -> Result<Vec<QifAction>>
+where
+    T: SplitAmountTransaction + std::fmt::Debug,
+{
+    if transaction.debit().trim().is_empty() && transaction.credit().trim().is_empty() {
+        println!("Skipping transaction without amount : {:#?}", transaction);
+        return Ok(Vec::new());
+    }
+    Ok(vec![QifAction::Generic {
+        date: transaction.get_date()?,
+        payee: transaction.payee(),
+        memo: transaction.memo(),
+        category: transaction.category(),
+        amount: signed_amount(transaction.debit(), transaction.credit())?,
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_amount() -> Result<()> {
+        assert_eq!(signed_amount("12.34", "")?, "-12.34");
+        assert_eq!(signed_amount("", "1,000.00")?, "1000.00");
+        // citi writes credits as negative numbers.
+        assert_eq!(signed_amount("", "-25.00")?, "25.00");
+        assert!(signed_amount(" ", "").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use calamine::{open_workbook_auto, Data, Range, Reader as WorkbookReader};
+use stable_eyre::eyre::*;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::result::Result::Ok;
+
+// Spreadsheets (.xlsx, .xls, .ods) are turned into csv so that the csv readers work on
+// them unchanged.   The row holding a csv header is written as that header exactly as
+// registered, quotes and all, for find_matching_line to find it.   Dates in the rows
+// after it are written in the date format of that header's reader, and in the usual US
+// format before any header.
+pub fn is_spreadsheet(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["xlsx", "xlsm", "xlsb", "xls", "ods"].contains(&extension.to_lowercase().as_str())
+        })
+}
+
+// the first worksheet, or the one named.
+pub fn read_spreadsheet_to_cursor(
+    path: &PathBuf,
+    worksheet: &Option<String>,
+    csv_headers: &[(String, String)],
+) -> Result<Cursor<Vec<u8>>> {
+    let mut workbook = open_workbook_auto(path)
+        .with_context(|| format!("Unable to open spreadsheet : {:?}", path.to_str()))?;
+    let range = match worksheet {
+        Some(name) => {
+            let sheet_names = workbook.sheet_names();
+            if !sheet_names.contains(name) {
+                return Err(eyre!(
+                    "No worksheet named \"{}\" found in {:?}, worksheets are : {:?}",
+                    name,
+                    path.to_str(),
+                    sheet_names
+                ));
+            }
+            workbook.worksheet_range(name)?
+        }
+        None => workbook
+            .worksheet_range_at(0)
+            .ok_or(eyre!("No worksheet found in {:?}", path.to_str()))??,
+    };
+    Ok(Cursor::new(range_to_csv(&range, csv_headers)?))
+}
+
+fn cell_to_string(cell: &Data, date_format: &str) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::String(value) | Data::DateTimeIso(value) | Data::DurationIso(value) => {
+            value.trim().to_string()
+        }
+        Data::Float(value) => value.to_string(),
+        Data::Int(value) => value.to_string(),
+        Data::Bool(value) => value.to_string(),
+        Data::DateTime(value) => match value.as_datetime() {
+            Some(datetime) if value.is_datetime() => datetime.format(date_format).to_string(),
+            _ => value.as_f64().to_string(),
+        },
+    }
+}
+
+// the fields of each header, without the empty fields some end with.
+fn header_fields(csv_header: &str) -> Result<Vec<String>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(csv_header.as_bytes());
+    let mut fields: Vec<String> = match rdr.records().next() {
+        Some(record) => record?
+            .iter()
+            .map(|field| field.trim().to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+    while fields.last().is_some_and(|field| field.is_empty()) {
+        fields.pop();
+    }
+    Ok(fields)
+}
+
+fn range_to_csv(range: &Range<Data>, csv_headers: &[(String, String)]) -> Result<Vec<u8>> {
+    let mut headers: Vec<(Vec<String>, &String, &String)> = Vec::new();
+    for (csv_header, date_format) in csv_headers {
+        headers.push((header_fields(csv_header)?, csv_header, date_format));
+    }
+
+    let mut date_format = "%m/%d/%Y";
+    let mut output: Vec<u8> = Vec::new();
+    for row in range.rows() {
+        let mut fields: Vec<String> = row
+            .iter()
+            .map(|cell| cell_to_string(cell, date_format))
+            .collect();
+        while fields.last().is_some_and(|field| field.is_empty()) {
+            fields.pop();
+        }
+        match headers.iter().find(|(header, _, _)| *header == fields) {
+            Some((_, csv_header, header_date_format)) => {
+                date_format = header_date_format;
+                output.extend_from_slice(csv_header.as_bytes());
+            }
+            None if fields.is_empty() => {}
+            None => {
+                let mut writer = csv::WriterBuilder::new()
+                    .flexible(true)
+                    .terminator(csv::Terminator::Any(b'\n'))
+                    .from_writer(&mut output);
+                writer.write_record(&fields)?;
+                writer.flush()?;
+                continue;
+            }
+        }
+        output.push(b'\n');
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_reader::Reader;
+    use crate::empower_reader::EmpowerReader;
+    use crate::schwab_reader::SchwabReader;
+
+    #[test]
+    fn test_range_to_csv() -> Result<()> {
+        let header = SchwabReader {}.csv_header();
+        let mut range: Range<Data> = Range::new((0, 0), (2, 7));
+        range.set_value(
+            (0, 0),
+            Data::String("Transactions for account XXXX-1234".into()),
+        );
+        let columns = [
+            "Date",
+            "Action",
+            "Symbol",
+            "Description",
+            "Price",
+            "Quantity",
+            "Fees & Comm",
+            "Amount",
+        ];
+        for (column, name) in columns.iter().enumerate() {
+            range.set_value((1, column as u32), Data::String(name.to_string()));
+        }
+        // 45306 is 01/15/2024 in excel's count of days.
+        range.set_value(
+            (2, 0),
+            Data::DateTime(calamine::ExcelDateTime::new(
+                45306.0,
+                calamine::ExcelDateTimeType::DateTime,
+                false,
+            )),
+        );
+        range.set_value((2, 1), Data::String("Buy".into()));
+        range.set_value((2, 2), Data::String("VTI".into()));
+        range.set_value((2, 3), Data::String("VANGUARD, TOTAL".into()));
+        range.set_value((2, 4), Data::Float(230.5));
+        range.set_value((2, 5), Data::Int(10));
+        range.set_value((2, 7), Data::Float(-2305.0));
+
+        let csv_headers = [(header.clone(), SchwabReader {}.date_format())];
+        let csv = String::from_utf8(range_to_csv(&range, &csv_headers)?)?;
+        assert_eq!(
+            csv,
+            "Transactions for account XXXX-1234\n".to_string()
+                + header.as_str()
+                + "\n"
+                + "01/15/2024,Buy,VTI,\"VANGUARD, TOTAL\",230.5,10,,-2305\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_to_csv_date_format() -> Result<()> {
+        let header = EmpowerReader {}.csv_header();
+        let mut range: Range<Data> = Range::new((0, 0), (1, 5));
+        for (column, name) in header.split(',').enumerate() {
+            range.set_value((0, column as u32), Data::String(name.to_string()));
+        }
+        // 45306.75 is 01/15/2024 at 6pm, empower has no times.
+        range.set_value(
+            (1, 0),
+            Data::DateTime(calamine::ExcelDateTime::new(
+                45306.75,
+                calamine::ExcelDateTimeType::DateTime,
+                false,
+            )),
+        );
+        range.set_value((1, 2), Data::String("Grocery Store".into()));
+        range.set_value((1, 5), Data::Float(-42.5));
+
+        let csv_headers = [(header.clone(), EmpowerReader {}.date_format())];
+        let csv = String::from_utf8(range_to_csv(&range, &csv_headers)?)?;
+        assert_eq!(csv, header + "\n" + "2024-01-15,,Grocery Store,,,-42.5\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_spreadsheet() {
+        assert!(is_spreadsheet(Path::new("export.XLSX")));
+        assert!(is_spreadsheet(Path::new("export.xls")));
+        assert!(!is_spreadsheet(Path::new("export.csv")));
+    }
+}
@@ -37,17 +37,7 @@ impl Symbols {
             let symbol = &security_cap[2];
             let name = &security_cap[1];
             let security_type_str = &security_cap[3];
-            let security_type = match security_type_str {
-                "Option" => Ok(SecurityType::Option),
-                "Stock" => Ok(SecurityType::Stock),
-                "Mutual Fund" => Ok(SecurityType::MutualFund),
-                "Market Index" => Ok(SecurityType::MarketIndex),
-                _ => {
-                    let err_msg: String =
-                        "unrecognized security type: ".to_string() + security_type_str;
-                    Err(eyre!(err_msg))
-                }
-            }?;
+            let security_type = SecurityType::from_qif(security_type_str)?;
             match base_symbols.entry(symbol.to_string()) {
                 Entry::Occupied(o) => {
                     if o.get().0 != name {
@@ -77,13 +67,30 @@ impl Symbols {
             Some((name, _)) => Ok(name.clone()),
             None => {
                 let (name, _) = self.new_symbols.get(symbol).ok_or(eyre!(
-                    "expected to find symbol in map: ".to_string() + symbol
+                    "expected to find symbol in map: ".to_string() + symbol.as_str()
                 ))?;
                 Ok(name.clone())
             }
         }
     }
 
+    pub fn lookup_type(&self, symbol: &str) -> Option<SecurityType> {
+        self.base_symbols
+            .get(symbol)
+            .or_else(|| self.new_symbols.get(symbol))
+            .map(|(_, security_type)| security_type.clone())
+    }
+
+    // the symbol of the security with this name, for input naming securities only by name.
+    pub fn find_symbol(&self, name: &str) -> Option<String> {
+        self.base_symbols
+            .iter()
+            .chain(self.new_symbols.iter())
+            .filter(|(_, (symbol_name, _))| symbol_name == name)
+            .map(|(symbol, _)| symbol.clone())
+            .min()
+    }
+
     pub fn enter_if_not_found(
         &mut self,
         symbol: &str,
@@ -112,7 +119,6 @@ impl Symbols {
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
     use std::io::Write;
@@ -136,7 +142,7 @@ mod tests {
         )
         .unwrap();
 
-        let symbols = Symbols::new(&temp_file.path().to_path_buf()).unwrap();
+        let symbols = Symbols::new(temp_file.path()).unwrap();
 
         // Test that the expected symbols were read from the file
         assert_eq!(
@@ -265,7 +271,7 @@ mod tests {
 
         let new_securities = symbols.get_new_securities().unwrap();
         assert_eq!(new_securities.len(), 1);
-        let (new_symbol, (new_name, new_security_type)) = new_securities.get(0).unwrap();
+        let (new_symbol, (new_name, new_security_type)) = new_securities.first().unwrap();
         assert_eq!(new_symbol, &symbol);
         assert_eq!(new_name, &name);
         assert_eq!(new_security_type, &security_type);
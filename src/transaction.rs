@@ -5,3 +5,8 @@ use stable_eyre::eyre::*;
 pub trait Transaction {
     fn to_qif_action(&self, securities: &mut Option<Symbols>) -> Result<Vec<QifAction>>;
 }
+
+// Transactions of csv files holding several accounts, such as those of aggregators.
+pub trait AccountTransaction: Transaction {
+    fn account(&self) -> String;
+}
@@ -0,0 +1,580 @@
+use chrono::NaiveDate;
+use stable_eyre::eyre::*;
+use std::fs::File;
+use std::io::Write as IoWrite;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::file_names::FileNames;
+use crate::opt::AccountType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// Ledger-cli / hledger journal output, every account in one journal.
+//
+// Securities are commodities named as Symbols::lookup names them, quoted since the names
+// have spaces : 10 "Apple Inc." @ $185.50.   Fees, margin interest and payments without
+// a category go under the expense account, dividends, interest, capital gains and
+// deposits without a category under the income account, both set on the command line.
+// On a credit card, a payment without a category is a transfer from Assets:Unknown.
+// Categories keep their qif hierarchy (Auto:Fuel becomes Expenses:Auto:Fuel), transfers
+// ([Savings]) go to the asset account of that name.
+
+// ledger wants the sign before the commodity : -$12.34
+fn dollars(amount: f64) -> String {
+    if amount < 0.0 {
+        format!("-${}", format_amount(-amount))
+    } else {
+        format!("${}", format_amount(amount))
+    }
+}
+
+struct LedgerWriter<'a> {
+    output: &'a mut dyn IoWrite,
+    symbols: Option<&'a Symbols>,
+    account: String,
+    cash_account: String,
+    income_account: &'a str,
+    expense_account: &'a str,
+}
+
+impl<'a> LedgerWriter<'a> {
+    fn security_name(&self, symbol: &String) -> Result<String> {
+        let name = self
+            .symbols
+            .ok_or(eyre!("Expected symbols but none provided."))?
+            .lookup(symbol)?;
+        Ok(name.replace('"', ""))
+    }
+
+    fn commodity(&self, symbol: &String) -> Result<String> {
+        Ok(format!("\"{}\"", self.security_name(symbol)?))
+    }
+
+    fn write_header(&mut self, date: &NaiveDate, payee: &str, memo: Option<&str>) -> Result<()> {
+        writeln!(self.output)?;
+        writeln!(self.output, "{} {}", date.format("%Y-%m-%d"), payee)?;
+        if let Some(memo) = memo.filter(|memo| !memo.is_empty()) {
+            writeln!(self.output, "    ; {}", memo)?;
+        }
+        Ok(())
+    }
+
+    // an empty amount is left for ledger to balance.
+    fn write_posting(&mut self, account: &str, amount: &str) -> Result<()> {
+        if amount.is_empty() {
+            writeln!(self.output, "    {}", account)?;
+        } else {
+            writeln!(self.output, "    {:<36}  {}", account, amount)?;
+        }
+        Ok(())
+    }
+
+    // the price per share when it accounts for the cost to the cent, else the total cost,
+    // so that the transaction balances whatever rounding the broker did.
+    fn priced(&self, quantity: f64, symbol: &String, price: &str, cost: f64) -> Result<String> {
+        let commodity = self.commodity(symbol)?;
+        let price = parse_amount(price)?;
+        if price != 0.0 && (quantity.abs() * price - cost).abs() < 0.005 {
+            Ok(format!(
+                "{} {} @ ${}",
+                format_quantity(quantity),
+                commodity,
+                format_quantity(price)
+            ))
+        } else {
+            Ok(format!(
+                "{} {} @@ {}",
+                format_quantity(quantity),
+                commodity,
+                dollars(cost)
+            ))
+        }
+    }
+
+    fn category_account(&self, category: &Option<String>, amount: f64) -> String {
+        let root = if amount > 0.0 {
+            self.income_account
+        } else {
+            self.expense_account
+        };
+        match category {
+            Some(category) if category.starts_with('[') && category.ends_with(']') => {
+                format!("Assets:{}", &category[1..category.len() - 1])
+            }
+            Some(category) => format!("{}:{}", root, category),
+            None if amount > 0.0 && self.account.starts_with("Liabilities:") => {
+                "Assets:Unknown".to_string()
+            }
+            None => format!("{}:Unknown", root),
+        }
+    }
+
+    // sign is 1 for buys, -1 for sells.
+    fn write_trade(&mut self, label: &str, trade: &Trade, cash: &str, sign: f64) -> Result<()> {
+        let quantity = parse_amount(&trade.quantity)?.abs();
+        let amount = parse_amount(&trade.amount)?.abs();
+        let fees = parse_amount(&trade.fees)?.abs();
+        let cost = amount - sign * fees;
+        let payee = format!("{} {}", label, self.security_name(&trade.symbol)?);
+        let shares = self.priced(sign * quantity, &trade.symbol, &trade.price, cost)?;
+        self.write_header(&trade.date, &payee, trade.memo.as_deref())?;
+        self.write_posting(&self.account.clone(), &shares)?;
+        if fees != 0.0 {
+            self.write_posting(&format!("{}:Fees", self.expense_account), &dollars(fees))?;
+        }
+        self.write_posting(cash, &dollars(-sign * amount))
+    }
+
+    fn write_reinvest(&mut self, label: &str, trade: &Trade, income: &str) -> Result<()> {
+        let amount = parse_amount(&trade.amount)?.abs();
+        let quantity = parse_amount(&trade.quantity)?.abs();
+        let payee = format!("{} {}", label, self.security_name(&trade.symbol)?);
+        let shares = self.priced(quantity, &trade.symbol, &trade.price, amount)?;
+        self.write_header(&trade.date, &payee, trade.memo.as_deref())?;
+        self.write_posting(&self.account.clone(), &shares)?;
+        self.write_posting(
+            &format!("{}:{}", self.income_account, income),
+            &dollars(-amount),
+        )
+    }
+
+    fn write_income(
+        &mut self,
+        label: &str,
+        date: &NaiveDate,
+        symbol: &String,
+        memo: Option<&str>,
+        income: &str,
+        amount: &str,
+    ) -> Result<()> {
+        let amount = parse_amount(amount)?;
+        let payee = format!("{} {}", label, self.security_name(symbol)?);
+        self.write_header(date, &payee, memo)?;
+        self.write_posting(&self.cash_account.clone(), &dollars(amount))?;
+        self.write_posting(
+            &format!("{}:{}", self.income_account, income),
+            &dollars(-amount),
+        )
+    }
+
+    fn write_shares(
+        &mut self,
+        label: &str,
+        date: &NaiveDate,
+        symbol: &String,
+        quantity: &str,
+        price: &Option<String>,
+        sign: f64,
+    ) -> Result<()> {
+        let quantity = sign * parse_amount(quantity)?.abs();
+        let shares = match price {
+            Some(price) => format!(
+                "{} {} @ ${}",
+                format_quantity(quantity),
+                self.commodity(symbol)?,
+                format_quantity(parse_amount(price)?)
+            ),
+            None => format!("{} {}", format_quantity(quantity), self.commodity(symbol)?),
+        };
+        let payee = format!("{} {}", label, self.security_name(symbol)?);
+        self.write_header(date, &payee, None)?;
+        self.write_posting(&self.account.clone(), &shares)?;
+        self.write_posting("Equity:Transfers", "")
+    }
+
+    // writes with the cash of the investment account itself rather than the linked account.
+    fn with_own_cash<F>(&mut self, write: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        let cash_account = std::mem::replace(&mut self.cash_account, self.account.clone());
+        let res = write(self);
+        self.cash_account = cash_account;
+        res
+    }
+
+    fn write_action(&mut self, action: &QifAction) -> Result<()> {
+        let cash = self.cash_account.clone();
+        match action {
+            QifAction::Buy { trade } => self.write_trade("Buy", trade, &cash, 1.0),
+            // paid from the cash of the investment account itself.
+            QifAction::BuyUnlinked { trade } => {
+                self.write_trade("Buy", trade, &self.account.clone(), 1.0)
+            }
+            QifAction::CvrShrt { trade } => self.write_trade("Cover short", trade, &cash, 1.0),
+            QifAction::Sell { trade } => self.write_trade("Sell", trade, &cash, -1.0),
+            QifAction::ShtSell { trade } => self.write_trade("Sell short", trade, &cash, -1.0),
+            QifAction::ReinvDiv { trade } => {
+                self.write_reinvest("Reinvest dividend", trade, "Dividends")
+            }
+            QifAction::ReinvInt { trade } => {
+                self.write_reinvest("Reinvest interest", trade, "Interest")
+            }
+            QifAction::ReinvLg { trade } => {
+                self.write_reinvest("Reinvest long-term gain", trade, "Capital Gains:Long")
+            }
+            QifAction::ReinvSh { trade } => {
+                self.write_reinvest("Reinvest short-term gain", trade, "Capital Gains:Short")
+            }
+            QifAction::Div {
+                date,
+                symbol,
+                amount,
+            } => self.write_income("Dividend", date, symbol, None, "Dividends", amount),
+            QifAction::IntInc {
+                date,
+                symbol,
+                amount,
+            } => self.write_income("Interest", date, symbol, None, "Interest", amount),
+            QifAction::CGLong {
+                date,
+                symbol,
+                amount,
+            } => self.write_income(
+                "Long-term gain",
+                date,
+                symbol,
+                None,
+                "Capital Gains:Long",
+                amount,
+            ),
+            QifAction::CGShort {
+                date,
+                symbol,
+                amount,
+            } => self.write_income(
+                "Short-term gain",
+                date,
+                symbol,
+                None,
+                "Capital Gains:Short",
+                amount,
+            ),
+            // never linked, the buy that usually follows pays from the account itself.
+            QifAction::MiscInc {
+                date,
+                symbol,
+                memo,
+                amount,
+            } => self.with_own_cash(|writer| {
+                writer.write_income("Income", date, symbol, Some(memo), "Misc", amount)
+            }),
+            QifAction::ShrsIn {
+                date,
+                symbol,
+                quantity,
+                price,
+                ..
+            } => self.write_shares("Shares in", date, symbol, quantity, price, 1.0),
+            QifAction::ShrsOut {
+                date,
+                symbol,
+                quantity,
+                price,
+                ..
+            } => self.write_shares("Shares out", date, symbol, quantity, price, -1.0),
+            QifAction::MargInt { date, memo, amount } => {
+                let amount = parse_amount(amount)?.abs();
+                self.write_header(date, "Margin interest", Some(memo))?;
+                self.write_posting(
+                    &format!("{}:Margin Interest", self.expense_account),
+                    &dollars(amount),
+                )?;
+                self.write_posting(&cash, &dollars(-amount))
+            }
+            QifAction::Generic {
+                date,
+                payee,
+                memo,
+                category,
+                amount,
+            } => {
+                let amount = parse_amount(amount)?;
+                self.write_header(date, payee, memo.as_deref())?;
+                self.write_posting(&self.account.clone(), &dollars(amount))?;
+                self.write_posting(&self.category_account(category, amount), "")
+            }
+            QifAction::Split {
+                date,
+                payee,
+                memo,
+                amount,
+                splits,
+            } => {
+                self.write_header(date, payee, memo.as_deref())?;
+                self.write_posting(&self.account.clone(), &dollars(parse_amount(amount)?))?;
+                for split in splits {
+                    let amount = parse_amount(&split.amount)?;
+                    let category = Some(split.category.clone()).filter(|c| !c.is_empty());
+                    let mut posting = dollars(-amount);
+                    if let Some(memo) = &split.memo {
+                        posting = format!("{}  ; {}", posting, memo);
+                    }
+                    self.write_posting(&self.category_account(&category, amount), &posting)?;
+                }
+                Ok(())
+            }
+            QifAction::Unlinked { action } => {
+                self.with_own_cash(|writer| writer.write_action(action))
+            }
+        }
+    }
+}
+
+impl QifTransactions {
+    // account is the ledger name of the main account, the other accounts found in the
+    // transactions file are named as found there.
+    pub fn print_ledger(
+        &self,
+        file_names: &FileNames,
+        account: &str,
+        linked_account: &Option<String>,
+        income_account: &str,
+        expense_account: &str,
+    ) -> Result<()> {
+        let mut output = File::create(&file_names.transactions_qif).with_context(|| {
+            format!(
+                "unable to create journal file : {:#?}",
+                &file_names.transactions_qif
+            )
+        })?;
+        self.write_ledger(
+            &mut output,
+            account,
+            linked_account,
+            income_account,
+            expense_account,
+        )?;
+
+        let count = self.qif_actions.len()
+            + self
+                .account_actions
+                .iter()
+                .map(|(_, qif_actions)| qif_actions.len())
+                .sum::<usize>();
+        println!("{} transaction(s) found.", count);
+        println!(
+            "For these transactions, include '{}' in your journal.",
+            file_names.transactions_qif.as_path().display()
+        );
+        println!(" ");
+        Ok(())
+    }
+
+    fn write_ledger(
+        &self,
+        output: &mut dyn IoWrite,
+        account: &str,
+        linked_account: &Option<String>,
+        income_account: &str,
+        expense_account: &str,
+    ) -> Result<()> {
+        let root = match self.account_type {
+            AccountType::CreditCard => "Liabilities",
+            AccountType::Cash | AccountType::Invest | AccountType::Retirement => "Assets",
+        };
+        let mut accounts: Vec<(String, &Vec<QifAction>)> =
+            vec![(format!("{}:{}", root, account), &self.qif_actions)];
+        for (name, qif_actions) in &self.account_actions {
+            accounts.push((format!("{}:{}", root, name), qif_actions));
+        }
+
+        writeln!(output, "; generated by csv2qif")?;
+        for (account, qif_actions) in accounts {
+            let cash_account = match linked_account {
+                Some(linked) => format!("Assets:{}", linked),
+                None => account.clone(),
+            };
+            let mut writer = LedgerWriter {
+                output: &mut *output,
+                symbols: self.symbols.as_ref(),
+                account,
+                cash_account,
+                income_account,
+                expense_account,
+            };
+            // csv files are often newest first, journals read oldest first.
+            let mut qif_actions: Vec<&QifAction> = qif_actions.iter().collect();
+            qif_actions.sort_by_key(|action| action.date());
+            for action in qif_actions {
+                writer.write_action(action)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_ledger() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let transactions = QifTransactions {
+            qif_actions: vec![
+                QifAction::Buy {
+                    trade: Trade {
+                        date,
+                        symbol: "AAPL".to_string(),
+                        price: "185.5".to_string(),
+                        quantity: "10".to_string(),
+                        amount: "-1859.95".to_string(),
+                        fees: "4.95".to_string(),
+                        memo: None,
+                    },
+                },
+                QifAction::Div {
+                    date,
+                    symbol: "AAPL".to_string(),
+                    amount: "2.40".to_string(),
+                },
+                QifAction::Split {
+                    date,
+                    payee: "Grocer".to_string(),
+                    memo: None,
+                    amount: "-30.00".to_string(),
+                    splits: vec![
+                        SplitLine {
+                            category: "Food".to_string(),
+                            memo: Some("milk".to_string()),
+                            amount: "-20.00".to_string(),
+                        },
+                        SplitLine {
+                            category: "[Savings]".to_string(),
+                            memo: None,
+                            amount: "-10.00".to_string(),
+                        },
+                    ],
+                },
+            ],
+            account_actions: Vec::new(),
+            currencies: HashMap::new(),
+            account_type: AccountType::Invest,
+            symbols: Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?),
+        };
+
+        let mut output: Vec<u8> = Vec::new();
+        transactions.write_ledger(
+            &mut output,
+            "Schwab",
+            &Some("Checking".to_string()),
+            "Income:Investments",
+            "Expenses",
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            r#"; generated by csv2qif
+
+2024-01-15 Buy Apple Inc.
+    Assets:Schwab                         10 "Apple Inc." @ $185.5
+    Expenses:Fees                         $4.95
+    Assets:Checking                       -$1859.95
+
+2024-01-15 Dividend Apple Inc.
+    Assets:Checking                       $2.40
+    Income:Investments:Dividends          -$2.40
+
+2024-01-15 Grocer
+    Assets:Schwab                         -$30.00
+    Expenses:Food                         $20.00  ; milk
+    Assets:Savings                        $10.00
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ledger_employer_match() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let transactions = QifTransactions {
+            qif_actions: vec![
+                QifAction::MiscInc {
+                    date,
+                    symbol: "AAPL".to_string(),
+                    memo: "Employer Match".to_string(),
+                    amount: "125.00".to_string(),
+                },
+                QifAction::BuyUnlinked {
+                    trade: Trade {
+                        date,
+                        symbol: "AAPL".to_string(),
+                        price: "125".to_string(),
+                        quantity: "1".to_string(),
+                        amount: "125.00".to_string(),
+                        fees: "".to_string(),
+                        memo: None,
+                    },
+                },
+            ],
+            account_actions: Vec::new(),
+            currencies: HashMap::new(),
+            account_type: AccountType::Retirement,
+            symbols: Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?),
+        };
+
+        // the match never goes through the linked account.
+        let mut output: Vec<u8> = Vec::new();
+        transactions.write_ledger(
+            &mut output,
+            "401k",
+            &Some("Checking".to_string()),
+            "Income",
+            "Expenses",
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            r#"; generated by csv2qif
+
+2024-01-15 Income Apple Inc.
+    ; Employer Match
+    Assets:401k                           $125.00
+    Income:Misc                           -$125.00
+
+2024-01-15 Buy Apple Inc.
+    Assets:401k                           1 "Apple Inc." @ $125
+    Assets:401k                           -$125.00
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ledger_credit_card() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let generic = |payee: &str, amount: &str| QifAction::Generic {
+            date,
+            payee: payee.to_string(),
+            memo: None,
+            category: None,
+            amount: amount.to_string(),
+        };
+        let transactions = QifTransactions {
+            qif_actions: vec![generic("Coffee", "-4.50"), generic("Payment", "500.00")],
+            account_actions: Vec::new(),
+            currencies: HashMap::new(),
+            account_type: AccountType::CreditCard,
+            symbols: None,
+        };
+
+        let mut output: Vec<u8> = Vec::new();
+        transactions.write_ledger(&mut output, "Visa", &None, "Income", "Expenses")?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            r#"; generated by csv2qif
+
+2024-01-15 Coffee
+    Liabilities:Visa                      -$4.50
+    Expenses:Unknown
+
+2024-01-15 Payment
+    Liabilities:Visa                      $500.00
+    Assets:Unknown
+"#
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,879 @@
+use chrono::NaiveDate;
+use stable_eyre::eyre::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Write as IoWrite;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::file_names::FileNames;
+use crate::opt::AccountType;
+use crate::option_symbol::parse_occ_symbol;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// OFX 1.02 (SGML) output, to be imported as .qfx.   Quicken takes investment transactions
+// more reliably from qfx than from qif.
+//
+// Everything of an account goes into one statement, the cash of an investment account
+// included, so there is no linked cash file.   OFX has no categories or splits, split
+// transactions are written with their total.   Securities are identified by their symbol
+// (UNIQUEIDTYPE TICKER) and listed in the SECLIST of investment statements, options with
+// the details read from their OCC symbol.   Option quantities are shares, written as
+// contracts of 100 shares.
+//
+// Each transaction gets a FITID hashed from its date, kind, symbol, quantity, amount and
+// payee, leaving out the account which only names the file unless given with --account-id.
+// Overlapping downloads of one account then give their common transactions the same
+// FITIDs, so that quicken skips those it already imported.
+//
+// Amounts are in the currency given with --currency, except for the accounts of readers
+// that split their file by currency (wise), which give the currency of each account.
+
+// 64 bit FNV-1a, simple and stable between runs and versions, unlike DefaultHasher.
+fn fnv1a(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+const SHARES_PER_CONTRACT: f64 = 100.0;
+
+fn trade_identity<'a>(
+    kind: &'static str,
+    trade: &'a Trade,
+) -> (&'static str, &'a str, &'a str, &'a str, &'a str) {
+    (kind, &trade.symbol, &trade.quantity, &trade.amount, "")
+}
+
+// the kind, symbol, quantity, amount and payee of a transaction, empty where it has none.
+fn identity(action: &QifAction) -> (&'static str, &str, &str, &str, &str) {
+    match action {
+        QifAction::ShtSell { trade } => trade_identity("ShtSell", trade),
+        QifAction::CvrShrt { trade } => trade_identity("CvrShrt", trade),
+        QifAction::Buy { trade } => trade_identity("Buy", trade),
+        QifAction::Sell { trade } => trade_identity("Sell", trade),
+        QifAction::BuyUnlinked { trade } => trade_identity("BuyUnlinked", trade),
+        QifAction::ReinvDiv { trade } => trade_identity("ReinvDiv", trade),
+        QifAction::ReinvInt { trade } => trade_identity("ReinvInt", trade),
+        QifAction::ReinvLg { trade } => trade_identity("ReinvLg", trade),
+        QifAction::ReinvSh { trade } => trade_identity("ReinvSh", trade),
+        QifAction::MargInt { memo, amount, .. } => ("MargInt", "", "", amount, memo),
+        QifAction::Div { symbol, amount, .. } => ("Div", symbol, "", amount, ""),
+        QifAction::IntInc { symbol, amount, .. } => ("IntInc", symbol, "", amount, ""),
+        QifAction::CGLong { symbol, amount, .. } => ("CGLong", symbol, "", amount, ""),
+        QifAction::CGShort { symbol, amount, .. } => ("CGShort", symbol, "", amount, ""),
+        QifAction::MiscInc {
+            symbol,
+            memo,
+            amount,
+            ..
+        } => ("MiscInc", symbol, "", amount, memo),
+        QifAction::ShrsIn {
+            symbol,
+            quantity,
+            amount,
+            ..
+        } => (
+            "ShrsIn",
+            symbol,
+            quantity,
+            amount.as_deref().unwrap_or(""),
+            "",
+        ),
+        QifAction::ShrsOut {
+            symbol,
+            quantity,
+            amount,
+            ..
+        } => (
+            "ShrsOut",
+            symbol,
+            quantity,
+            amount.as_deref().unwrap_or(""),
+            "",
+        ),
+        QifAction::Generic { payee, amount, .. } => ("Generic", "", "", amount, payee),
+        QifAction::Split { payee, amount, .. } => ("Split", "", "", amount, payee),
+        QifAction::Unlinked { action } => identity(action),
+    }
+}
+
+// identical transactions (two coffees on one day) are told apart by their count.
+#[derive(Default)]
+struct FitIds {
+    seen: HashMap<String, usize>,
+}
+
+impl FitIds {
+    fn next(&mut self, action: &QifAction) -> String {
+        let (kind, symbol, quantity, amount, payee) = identity(action);
+        let key = format!(
+            "{}|{}|{}|{}|{}|{}",
+            action.date(),
+            kind,
+            symbol,
+            quantity,
+            amount,
+            payee
+        );
+        let count = self.seen.entry(key.clone()).or_insert(0);
+        *count += 1;
+        format!("{:016X}", fnv1a(&format!("{}#{}", key, count)))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn ofx_date(date: &NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn symbol(action: &QifAction) -> Option<&String> {
+    match action {
+        QifAction::ShtSell { trade }
+        | QifAction::CvrShrt { trade }
+        | QifAction::Buy { trade }
+        | QifAction::Sell { trade }
+        | QifAction::BuyUnlinked { trade }
+        | QifAction::ReinvDiv { trade }
+        | QifAction::ReinvInt { trade }
+        | QifAction::ReinvLg { trade }
+        | QifAction::ReinvSh { trade } => Some(&trade.symbol),
+        QifAction::Div { symbol, .. }
+        | QifAction::IntInc { symbol, .. }
+        | QifAction::MiscInc { symbol, .. }
+        | QifAction::CGLong { symbol, .. }
+        | QifAction::CGShort { symbol, .. }
+        | QifAction::ShrsIn { symbol, .. }
+        | QifAction::ShrsOut { symbol, .. } => Some(symbol),
+        QifAction::MargInt { .. } | QifAction::Generic { .. } | QifAction::Split { .. } => None,
+        QifAction::Unlinked { action } => symbol(action),
+    }
+}
+
+struct QfxWriter<'a> {
+    output: &'a mut dyn IoWrite,
+    symbols: Option<&'a Symbols>,
+    fit_ids: FitIds,
+}
+
+impl<'a> QfxWriter<'a> {
+    // ofx has aggregates for stocks, mutual funds and options with an OCC symbol, the
+    // rest is "other".
+    fn security_kind(&self, symbol: &str) -> &'static str {
+        match self.symbols.and_then(|symbols| symbols.lookup_type(symbol)) {
+            Some(SecurityType::Stock) => "STOCK",
+            Some(SecurityType::MutualFund) => "MF",
+            Some(SecurityType::Option) if parse_occ_symbol(symbol).is_some() => "OPT",
+            _ => "OTHER",
+        }
+    }
+
+    fn write_invtran(
+        &mut self,
+        action: &QifAction,
+        date: &NaiveDate,
+        memo: Option<&str>,
+    ) -> Result<()> {
+        let fit_id = self.fit_ids.next(action);
+        writeln!(self.output, "<INVTRAN>")?;
+        writeln!(self.output, "<FITID>{}", fit_id)?;
+        writeln!(self.output, "<DTTRADE>{}", ofx_date(date))?;
+        if let Some(memo) = memo.filter(|memo| !memo.is_empty()) {
+            writeln!(self.output, "<MEMO>{}", escape(memo))?;
+        }
+        writeln!(self.output, "</INVTRAN>")?;
+        Ok(())
+    }
+
+    fn write_secid(&mut self, symbol: &str) -> Result<()> {
+        writeln!(self.output, "<SECID>")?;
+        writeln!(self.output, "<UNIQUEID>{}", escape(symbol))?;
+        writeln!(self.output, "<UNIQUEIDTYPE>TICKER")?;
+        writeln!(self.output, "</SECID>")?;
+        Ok(())
+    }
+
+    // the units and total of a trade, signed as ofx wants them.
+    fn write_trade_amounts(
+        &mut self,
+        trade: &Trade,
+        sign: f64,
+        shares_per_unit: f64,
+    ) -> Result<()> {
+        let fees = parse_amount(&trade.fees)?;
+        writeln!(
+            self.output,
+            "<UNITS>{}",
+            format_quantity(sign * parse_amount(&trade.quantity)?.abs() / shares_per_unit)
+        )?;
+        writeln!(
+            self.output,
+            "<UNITPRICE>{}",
+            format_quantity(parse_amount(&trade.price)?)
+        )?;
+        if fees != 0.0 {
+            writeln!(self.output, "<COMMISSION>{}", format_amount(fees))?;
+        }
+        writeln!(
+            self.output,
+            "<TOTAL>{}",
+            format_amount(-sign * parse_amount(&trade.amount)?.abs())
+        )?;
+        writeln!(self.output, "<SUBACCTSEC>CASH")?;
+        writeln!(self.output, "<SUBACCTFUND>CASH")?;
+        Ok(())
+    }
+
+    // a buy opens a position or covers a short one, only stocks and options can be covered.
+    fn write_buy(&mut self, action: &QifAction, trade: &Trade, covering: bool) -> Result<()> {
+        let kind = match self.security_kind(&trade.symbol) {
+            "OPT" => "OPT",
+            _ if covering => "STOCK",
+            kind => kind,
+        };
+        let shares_per_unit = if kind == "OPT" {
+            SHARES_PER_CONTRACT
+        } else {
+            1.0
+        };
+        writeln!(self.output, "<BUY{}>", kind)?;
+        writeln!(self.output, "<INVBUY>")?;
+        self.write_invtran(action, &trade.date, trade.memo.as_deref())?;
+        self.write_secid(&trade.symbol)?;
+        self.write_trade_amounts(trade, 1.0, shares_per_unit)?;
+        writeln!(self.output, "</INVBUY>")?;
+        match kind {
+            "OPT" => {
+                let buy_type = if covering { "BUYTOCLOSE" } else { "BUYTOOPEN" };
+                writeln!(self.output, "<OPTBUYTYPE>{}", buy_type)?;
+                writeln!(self.output, "<SHPERCTRCT>{}", SHARES_PER_CONTRACT)?;
+            }
+            "OTHER" => {}
+            _ => {
+                let buy_type = if covering { "BUYTOCOVER" } else { "BUY" };
+                writeln!(self.output, "<BUYTYPE>{}", buy_type)?;
+            }
+        }
+        writeln!(self.output, "</BUY{}>", kind)?;
+        Ok(())
+    }
+
+    // a sell closes a position or opens a short one, only stocks and options can be shorted.
+    fn write_sell(&mut self, action: &QifAction, trade: &Trade, shorting: bool) -> Result<()> {
+        let kind = match self.security_kind(&trade.symbol) {
+            "OPT" => "OPT",
+            _ if shorting => "STOCK",
+            kind => kind,
+        };
+        let shares_per_unit = if kind == "OPT" {
+            SHARES_PER_CONTRACT
+        } else {
+            1.0
+        };
+        writeln!(self.output, "<SELL{}>", kind)?;
+        writeln!(self.output, "<INVSELL>")?;
+        self.write_invtran(action, &trade.date, trade.memo.as_deref())?;
+        self.write_secid(&trade.symbol)?;
+        self.write_trade_amounts(trade, -1.0, shares_per_unit)?;
+        writeln!(self.output, "</INVSELL>")?;
+        match kind {
+            "OPT" => {
+                let sell_type = if shorting {
+                    "SELLTOOPEN"
+                } else {
+                    "SELLTOCLOSE"
+                };
+                writeln!(self.output, "<OPTSELLTYPE>{}", sell_type)?;
+                writeln!(self.output, "<SHPERCTRCT>{}", SHARES_PER_CONTRACT)?;
+            }
+            "OTHER" => {}
+            _ => {
+                let sell_type = if shorting { "SELLSHORT" } else { "SELL" };
+                writeln!(self.output, "<SELLTYPE>{}", sell_type)?;
+            }
+        }
+        writeln!(self.output, "</SELL{}>", kind)?;
+        Ok(())
+    }
+
+    fn write_reinvest(
+        &mut self,
+        action: &QifAction,
+        trade: &Trade,
+        income_type: &str,
+    ) -> Result<()> {
+        writeln!(self.output, "<REINVEST>")?;
+        self.write_invtran(action, &trade.date, trade.memo.as_deref())?;
+        self.write_secid(&trade.symbol)?;
+        writeln!(self.output, "<INCOMETYPE>{}", income_type)?;
+        writeln!(
+            self.output,
+            "<TOTAL>{}",
+            format_amount(-parse_amount(&trade.amount)?.abs())
+        )?;
+        writeln!(self.output, "<SUBACCTSEC>CASH")?;
+        writeln!(
+            self.output,
+            "<UNITS>{}",
+            format_quantity(parse_amount(&trade.quantity)?.abs())
+        )?;
+        writeln!(
+            self.output,
+            "<UNITPRICE>{}",
+            format_quantity(parse_amount(&trade.price)?)
+        )?;
+        writeln!(self.output, "</REINVEST>")?;
+        Ok(())
+    }
+
+    fn write_income(
+        &mut self,
+        action: &QifAction,
+        date: &NaiveDate,
+        symbol: &str,
+        memo: Option<&str>,
+        income_type: &str,
+        amount: &str,
+    ) -> Result<()> {
+        writeln!(self.output, "<INCOME>")?;
+        self.write_invtran(action, date, memo)?;
+        self.write_secid(symbol)?;
+        writeln!(self.output, "<INCOMETYPE>{}", income_type)?;
+        writeln!(
+            self.output,
+            "<TOTAL>{}",
+            format_amount(parse_amount(amount)?)
+        )?;
+        writeln!(self.output, "<SUBACCTSEC>CASH")?;
+        writeln!(self.output, "<SUBACCTFUND>CASH")?;
+        writeln!(self.output, "</INCOME>")?;
+        Ok(())
+    }
+
+    fn write_transfer(
+        &mut self,
+        action: &QifAction,
+        date: &NaiveDate,
+        symbol: &str,
+        quantity: &str,
+        price: &Option<String>,
+        shares_in: bool,
+    ) -> Result<()> {
+        let sign = if shares_in { 1.0 } else { -1.0 };
+        writeln!(self.output, "<TRANSFER>")?;
+        self.write_invtran(action, date, None)?;
+        self.write_secid(symbol)?;
+        writeln!(self.output, "<SUBACCTSEC>CASH")?;
+        writeln!(
+            self.output,
+            "<UNITS>{}",
+            format_quantity(sign * parse_amount(quantity)?.abs())
+        )?;
+        writeln!(
+            self.output,
+            "<TFERACTION>{}",
+            if shares_in { "IN" } else { "OUT" }
+        )?;
+        writeln!(self.output, "<POSTYPE>LONG")?;
+        if let Some(price) = price {
+            writeln!(
+                self.output,
+                "<UNITPRICE>{}",
+                format_quantity(parse_amount(price)?)
+            )?;
+        }
+        writeln!(self.output, "</TRANSFER>")?;
+        Ok(())
+    }
+
+    fn write_stmttrn(
+        &mut self,
+        action: &QifAction,
+        date: &NaiveDate,
+        payee: &str,
+        memo: &Option<String>,
+        amount: &str,
+    ) -> Result<()> {
+        let amount = parse_amount(amount)?;
+        let fit_id = self.fit_ids.next(action);
+        writeln!(self.output, "<STMTTRN>")?;
+        writeln!(
+            self.output,
+            "<TRNTYPE>{}",
+            if amount < 0.0 { "DEBIT" } else { "CREDIT" }
+        )?;
+        writeln!(self.output, "<DTPOSTED>{}", ofx_date(date))?;
+        writeln!(self.output, "<TRNAMT>{}", format_amount(amount))?;
+        writeln!(self.output, "<FITID>{}", fit_id)?;
+        // ofx 1.02 names are at most 32 characters.
+        let name: String = payee.chars().take(32).collect();
+        writeln!(self.output, "<NAME>{}", escape(&name))?;
+        if let Some(memo) = memo {
+            writeln!(self.output, "<MEMO>{}", escape(memo))?;
+        }
+        writeln!(self.output, "</STMTTRN>")?;
+        Ok(())
+    }
+
+    fn write_bank_action(&mut self, action: &QifAction) -> Result<()> {
+        match action {
+            QifAction::Generic {
+                date,
+                payee,
+                memo,
+                amount,
+                ..
+            }
+            | QifAction::Split {
+                date,
+                payee,
+                memo,
+                amount,
+                ..
+            } => self.write_stmttrn(action, date, payee, memo, amount),
+            _ => Err(eyre!(
+                "Investment transaction found for a bank or credit card account : {:?}",
+                action
+            )),
+        }
+    }
+
+    fn write_investment_action(&mut self, action: &QifAction) -> Result<()> {
+        match action {
+            QifAction::Buy { trade } | QifAction::BuyUnlinked { trade } => {
+                self.write_buy(action, trade, false)
+            }
+            QifAction::CvrShrt { trade } => self.write_buy(action, trade, true),
+            QifAction::Sell { trade } => self.write_sell(action, trade, false),
+            QifAction::ShtSell { trade } => self.write_sell(action, trade, true),
+            QifAction::ReinvDiv { trade } => self.write_reinvest(action, trade, "DIV"),
+            QifAction::ReinvInt { trade } => self.write_reinvest(action, trade, "INTEREST"),
+            QifAction::ReinvLg { trade } => self.write_reinvest(action, trade, "CGLONG"),
+            QifAction::ReinvSh { trade } => self.write_reinvest(action, trade, "CGSHORT"),
+            QifAction::Div {
+                date,
+                symbol,
+                amount,
+            } => self.write_income(action, date, symbol, None, "DIV", amount),
+            QifAction::IntInc {
+                date,
+                symbol,
+                amount,
+            } => self.write_income(action, date, symbol, None, "INTEREST", amount),
+            QifAction::CGLong {
+                date,
+                symbol,
+                amount,
+            } => self.write_income(action, date, symbol, None, "CGLONG", amount),
+            QifAction::CGShort {
+                date,
+                symbol,
+                amount,
+            } => self.write_income(action, date, symbol, None, "CGSHORT", amount),
+            QifAction::MiscInc {
+                date,
+                symbol,
+                memo,
+                amount,
+            } => self.write_income(action, date, symbol, Some(memo), "MISC", amount),
+            QifAction::ShrsIn {
+                date,
+                symbol,
+                quantity,
+                price,
+                ..
+            } => self.write_transfer(action, date, symbol, quantity, price, true),
+            QifAction::ShrsOut {
+                date,
+                symbol,
+                quantity,
+                price,
+                ..
+            } => self.write_transfer(action, date, symbol, quantity, price, false),
+            QifAction::MargInt { date, memo, amount } => {
+                writeln!(self.output, "<MARGININTEREST>")?;
+                self.write_invtran(action, date, Some(memo))?;
+                writeln!(
+                    self.output,
+                    "<TOTAL>{}",
+                    format_amount(-parse_amount(amount)?.abs())
+                )?;
+                writeln!(self.output, "<SUBACCTFUND>CASH")?;
+                writeln!(self.output, "</MARGININTEREST>")?;
+                Ok(())
+            }
+            // everything of the account is in the statement, linked or not.
+            QifAction::Unlinked { action } => self.write_investment_action(action),
+            QifAction::Generic { .. } | QifAction::Split { .. } => {
+                writeln!(self.output, "<INVBANKTRAN>")?;
+                self.write_bank_action(action)?;
+                writeln!(self.output, "<SUBACCTFUND>CASH")?;
+                writeln!(self.output, "</INVBANKTRAN>")?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_seclist(&mut self, qif_actions: &[QifAction]) -> Result<()> {
+        let securities: BTreeSet<&String> = qif_actions.iter().filter_map(symbol).collect();
+        if securities.is_empty() {
+            return Ok(());
+        }
+        let symbols = self
+            .symbols
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        writeln!(self.output, "<SECLISTMSGSRSV1>")?;
+        writeln!(self.output, "<SECLIST>")?;
+        for security in securities {
+            let kind = self.security_kind(security);
+            writeln!(self.output, "<{}INFO>", kind)?;
+            writeln!(self.output, "<SECINFO>")?;
+            self.write_secid(security)?;
+            writeln!(
+                self.output,
+                "<SECNAME>{}",
+                escape(&symbols.lookup(security)?)
+            )?;
+            writeln!(self.output, "<TICKER>{}", escape(security))?;
+            writeln!(self.output, "</SECINFO>")?;
+            if let ("OPT", Some((_, expiration, put_or_call, strike))) =
+                (kind, parse_occ_symbol(security))
+            {
+                let option_type = if put_or_call == "C" { "CALL" } else { "PUT" };
+                writeln!(self.output, "<OPTTYPE>{}", option_type)?;
+                writeln!(self.output, "<STRIKEPRICE>{}", format_quantity(strike))?;
+                writeln!(self.output, "<DTEXPIRE>{}", ofx_date(&expiration))?;
+                writeln!(self.output, "<SHPERCTRCT>{}", SHARES_PER_CONTRACT)?;
+            }
+            writeln!(self.output, "</{}INFO>", kind)?;
+        }
+        writeln!(self.output, "</SECLIST>")?;
+        writeln!(self.output, "</SECLISTMSGSRSV1>")?;
+        Ok(())
+    }
+}
+
+impl QifTransactions {
+    pub fn print_qfxs(
+        &self,
+        file_names: &FileNames,
+        account_id: &Option<String>,
+        currency: &str,
+    ) -> Result<()> {
+        self.print_qfx(&self.qif_actions, file_names, account_id, currency)?;
+        for (account, qif_actions) in &self.account_actions {
+            println!("Account '{}' :", account);
+            self.print_qfx(
+                qif_actions,
+                &file_names.for_account(account),
+                &account_id.as_ref().map(|id| format!("{}_{}", id, account)),
+                self.currencies
+                    .get(account)
+                    .map(String::as_str)
+                    .unwrap_or(currency),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_qfx(
+        &self,
+        qif_actions: &[QifAction],
+        file_names: &FileNames,
+        account_id: &Option<String>,
+        currency: &str,
+    ) -> Result<()> {
+        if qif_actions.is_empty() {
+            return Ok(());
+        }
+        // quicken asks which account to import into, the name of the file will do when no
+        // id is given.
+        let account_id = account_id.clone().unwrap_or_else(|| {
+            file_names
+                .transactions_qif
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        });
+        let mut output = File::create(&file_names.transactions_qif).with_context(|| {
+            format!(
+                "unable to create .qfx file : {:#?}",
+                &file_names.transactions_qif
+            )
+        })?;
+        self.write_qfx(qif_actions, &account_id, currency, &mut output)?;
+
+        println!("{} transaction(s) found.", qif_actions.len());
+        println!(
+            "For these transactions, import '{}' into the appropriate account.",
+            file_names.transactions_qif.as_path().display()
+        );
+        println!(" ");
+        Ok(())
+    }
+
+    fn write_qfx(
+        &self,
+        qif_actions: &[QifAction],
+        account_id: &str,
+        currency: &str,
+        output: &mut dyn IoWrite,
+    ) -> Result<()> {
+        let start = qif_actions
+            .iter()
+            .map(QifAction::date)
+            .min()
+            .unwrap_or_default();
+        let end = qif_actions
+            .iter()
+            .map(QifAction::date)
+            .max()
+            .unwrap_or_default();
+
+        writeln!(output, "OFXHEADER:100")?;
+        writeln!(output, "DATA:OFXSGML")?;
+        writeln!(output, "VERSION:102")?;
+        writeln!(output, "SECURITY:NONE")?;
+        writeln!(output, "ENCODING:USASCII")?;
+        writeln!(output, "CHARSET:1252")?;
+        writeln!(output, "COMPRESSION:NONE")?;
+        writeln!(output, "OLDFILEUID:NONE")?;
+        writeln!(output, "NEWFILEUID:NONE")?;
+        writeln!(output)?;
+        writeln!(output, "<OFX>")?;
+        writeln!(output, "<SIGNONMSGSRSV1>")?;
+        writeln!(output, "<SONRS>")?;
+        writeln!(output, "<STATUS>")?;
+        writeln!(output, "<CODE>0")?;
+        writeln!(output, "<SEVERITY>INFO")?;
+        writeln!(output, "</STATUS>")?;
+        // the date of the last transaction rather than now, for the same file every time.
+        writeln!(output, "<DTSERVER>{}", ofx_date(&end))?;
+        writeln!(output, "<LANGUAGE>ENG")?;
+        writeln!(output, "</SONRS>")?;
+        writeln!(output, "</SIGNONMSGSRSV1>")?;
+
+        let (messages, transaction_response, statement) = match self.account_type {
+            AccountType::Invest | AccountType::Retirement => {
+                ("INVSTMTMSGSRSV1", "INVSTMTTRNRS", "INVSTMTRS")
+            }
+            AccountType::Cash => ("BANKMSGSRSV1", "STMTTRNRS", "STMTRS"),
+            AccountType::CreditCard => ("CREDITCARDMSGSRSV1", "CCSTMTTRNRS", "CCSTMTRS"),
+        };
+        writeln!(output, "<{}>", messages)?;
+        writeln!(output, "<{}>", transaction_response)?;
+        writeln!(output, "<TRNUID>0")?;
+        writeln!(output, "<STATUS>")?;
+        writeln!(output, "<CODE>0")?;
+        writeln!(output, "<SEVERITY>INFO")?;
+        writeln!(output, "</STATUS>")?;
+        writeln!(output, "<{}>", statement)?;
+
+        let mut writer = QfxWriter {
+            output,
+            symbols: self.symbols.as_ref(),
+            fit_ids: FitIds::default(),
+        };
+        match self.account_type {
+            AccountType::Invest | AccountType::Retirement => {
+                writeln!(writer.output, "<DTASOF>{}", ofx_date(&end))?;
+                writeln!(writer.output, "<CURDEF>{}", currency)?;
+                writeln!(writer.output, "<INVACCTFROM>")?;
+                writeln!(writer.output, "<BROKERID>csv2qif")?;
+                writeln!(writer.output, "<ACCTID>{}", escape(account_id))?;
+                writeln!(writer.output, "</INVACCTFROM>")?;
+                writeln!(writer.output, "<INVTRANLIST>")?;
+                writeln!(writer.output, "<DTSTART>{}", ofx_date(&start))?;
+                writeln!(writer.output, "<DTEND>{}", ofx_date(&end))?;
+                for action in qif_actions {
+                    writer.write_investment_action(action)?;
+                }
+                writeln!(writer.output, "</INVTRANLIST>")?;
+            }
+            AccountType::Cash | AccountType::CreditCard => {
+                writeln!(writer.output, "<CURDEF>{}", currency)?;
+                if matches!(self.account_type, AccountType::Cash) {
+                    writeln!(writer.output, "<BANKACCTFROM>")?;
+                    writeln!(writer.output, "<BANKID>000000000")?;
+                    writeln!(writer.output, "<ACCTID>{}", escape(account_id))?;
+                    writeln!(writer.output, "<ACCTTYPE>CHECKING")?;
+                    writeln!(writer.output, "</BANKACCTFROM>")?;
+                } else {
+                    writeln!(writer.output, "<CCACCTFROM>")?;
+                    writeln!(writer.output, "<ACCTID>{}", escape(account_id))?;
+                    writeln!(writer.output, "</CCACCTFROM>")?;
+                }
+                writeln!(writer.output, "<BANKTRANLIST>")?;
+                writeln!(writer.output, "<DTSTART>{}", ofx_date(&start))?;
+                writeln!(writer.output, "<DTEND>{}", ofx_date(&end))?;
+                for action in qif_actions {
+                    writer.write_bank_action(action)?;
+                }
+                writeln!(writer.output, "</BANKTRANLIST>")?;
+            }
+        }
+
+        writeln!(writer.output, "</{}>", statement)?;
+        writeln!(writer.output, "</{}>", transaction_response)?;
+        writeln!(writer.output, "</{}>", messages)?;
+        writer.write_seclist(qif_actions)?;
+        writeln!(writer.output, "</OFX>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_reader::Reader;
+    use crate::ofx_reader::OfxReader;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn trade(date: NaiveDate, quantity: &str, amount: &str) -> Trade {
+        Trade {
+            date,
+            symbol: "AAPL".to_string(),
+            price: "185.5".to_string(),
+            quantity: quantity.to_string(),
+            amount: amount.to_string(),
+            fees: "".to_string(),
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn test_qfx_round_trip() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let coffee = || QifAction::Generic {
+            date,
+            payee: "Coffee & Co".to_string(),
+            memo: None,
+            category: None,
+            amount: "-4.50".to_string(),
+        };
+        let option = "AAPL  240119C00150000".to_string();
+        let mut symbols = Symbols::new(&PathBuf::from("test_data/securities.txt"))?;
+        symbols.enter_if_not_found(&option, "CALL : APPLE INC", &SecurityType::Option)?;
+        let transactions = QifTransactions {
+            qif_actions: vec![
+                QifAction::Buy {
+                    trade: trade(date, "10", "1855.00"),
+                },
+                QifAction::Sell {
+                    trade: trade(date, "4", "742.00"),
+                },
+                QifAction::Div {
+                    date,
+                    symbol: "AAPL".to_string(),
+                    amount: "2.40".to_string(),
+                },
+                coffee(),
+                coffee(),
+                QifAction::ShtSell {
+                    trade: Trade {
+                        symbol: option.clone(),
+                        price: "1.5".to_string(),
+                        ..trade(date, "200", "300.00")
+                    },
+                },
+                QifAction::CvrShrt {
+                    trade: Trade {
+                        symbol: option.clone(),
+                        price: "0.25".to_string(),
+                        ..trade(date, "200", "50.00")
+                    },
+                },
+            ],
+            account_actions: Vec::new(),
+            currencies: HashMap::new(),
+            account_type: AccountType::Invest,
+            symbols: Some(symbols),
+        };
+
+        let mut output: Vec<u8> = Vec::new();
+        transactions.write_qfx(
+            &transactions.qif_actions,
+            "invest_statement",
+            "EUR",
+            &mut output,
+        )?;
+        let mut again: Vec<u8> = Vec::new();
+        transactions.write_qfx(
+            &transactions.qif_actions,
+            "invest_statement",
+            "EUR",
+            &mut again,
+        )?;
+        // fitids are the same every time, and different for identical transactions.
+        assert_eq!(output, again);
+        let qfx = String::from_utf8(output)?;
+        // or when converted from a file with another name.
+        let mut renamed: Vec<u8> = Vec::new();
+        transactions.write_qfx(
+            &transactions.qif_actions,
+            "feb_statement",
+            "EUR",
+            &mut renamed,
+        )?;
+        let renamed = String::from_utf8(renamed)?;
+        assert!(renamed.contains("<ACCTID>feb_statement"));
+        assert!(qfx
+            .lines()
+            .filter(|l| l.starts_with("<FITID>"))
+            .eq(renamed.lines().filter(|l| l.starts_with("<FITID>"))));
+        let fit_ids: BTreeSet<&str> = qfx.lines().filter(|l| l.starts_with("<FITID>")).collect();
+        assert_eq!(fit_ids.len(), 7);
+        assert!(qfx.contains("<CURDEF>EUR"));
+        assert!(qfx.contains("<BUYSTOCK>"));
+        assert!(qfx.contains("<SECNAME>Apple Inc."));
+        // options are traded in contracts.
+        assert!(qfx.contains("<UNITS>-2\n<UNITPRICE>1.5\n"));
+        assert!(qfx.contains("<OPTSELLTYPE>SELLTOOPEN"));
+        assert!(qfx.contains("<OPTBUYTYPE>BUYTOCLOSE"));
+        assert!(qfx.contains("<OPTTYPE>CALL\n<STRIKEPRICE>150\n<DTEXPIRE>20240119\n"));
+
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = OfxReader {}.to_transactions(&mut Cursor::new(qfx), &mut symbols)?;
+        assert_eq!(actions.len(), 7);
+        match (&actions[0], &actions[1], &actions[4]) {
+            (
+                QifAction::Buy { trade: buy },
+                QifAction::Sell { trade: sell },
+                QifAction::Generic { payee, amount, .. },
+            ) => {
+                assert_eq!(buy.quantity, "10");
+                assert_eq!(buy.amount, "1855.00");
+                assert_eq!(sell.quantity, "4");
+                assert_eq!(payee, "Coffee & Co");
+                assert_eq!(amount, "-4.50");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        match (&actions[5], &actions[6]) {
+            (QifAction::ShtSell { trade: open }, QifAction::CvrShrt { trade: close }) => {
+                assert_eq!(open.symbol, option);
+                assert_eq!(open.quantity, "200");
+                assert_eq!(open.amount, "300.00");
+                assert_eq!(close.quantity, "200");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        assert_eq!(
+            symbols.unwrap().lookup_type(&option),
+            Some(SecurityType::Option)
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::{Datelike, NaiveDate};
 use stable_eyre::eyre::*;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write as IoWrite;
 use std::path::PathBuf;
@@ -17,6 +18,7 @@ pub struct Trade {
     pub quantity: String,
     pub amount: String,
     pub fees: String,
+    pub memo: Option<String>, // replaces the security name as memo when present.
 }
 
 impl Trade {
@@ -46,7 +48,7 @@ impl Trade {
         writeln!(output, "Q{}", self.quantity)?;
         writeln!(output, "U{}", self.amount)?;
         writeln!(output, "T{}", self.amount)?;
-        writeln!(output, "M{}", memo)?;
+        writeln!(output, "M{}", self.memo.as_ref().unwrap_or(&memo))?;
         writeln!(output, "O{}", self.fees)?;
         if let Some(acctname) = linked_account {
             writeln!(output, "L[{}]", acctname)?
@@ -57,6 +59,14 @@ impl Trade {
     }
 }
 
+// one category of a split transaction.
+#[derive(Debug)]
+pub struct SplitLine {
+    pub category: String,
+    pub memo: Option<String>,
+    pub amount: String,
+}
+
 #[derive(Debug)]
 pub enum QifAction {
     ShtSell {
@@ -71,6 +81,22 @@ pub enum QifAction {
     Sell {
         trade: Trade,
     },
+    BuyUnlinked {
+        trade: Trade, // paid from cash already in the account, never from the linked account.
+    },
+    // income used to buy more of the security that paid it, never linked.
+    ReinvDiv {
+        trade: Trade,
+    },
+    ReinvInt {
+        trade: Trade,
+    },
+    ReinvLg {
+        trade: Trade,
+    },
+    ReinvSh {
+        trade: Trade,
+    },
     MargInt {
         date: NaiveDate,
         memo: String,
@@ -81,6 +107,17 @@ pub enum QifAction {
         symbol: String,
         amount: String,
     },
+    IntInc {
+        date: NaiveDate,
+        symbol: String,
+        amount: String,
+    },
+    MiscInc {
+        date: NaiveDate,
+        symbol: String,
+        memo: String,
+        amount: String,
+    },
     CGLong {
         date: NaiveDate,
         symbol: String,
@@ -94,7 +131,16 @@ pub enum QifAction {
     ShrsIn {
         date: NaiveDate,
         symbol: String,
-        quantity: i32,
+        quantity: String,
+        price: Option<String>,  // cost basis per share, when known.
+        amount: Option<String>, // total cost basis, when known.
+    },
+    ShrsOut {
+        date: NaiveDate,
+        symbol: String,
+        quantity: String,
+        price: Option<String>,  // cost basis per share, when known.
+        amount: Option<String>, // total cost basis, when known.
     },
     Generic {
         date: NaiveDate,
@@ -103,6 +149,17 @@ pub enum QifAction {
         category: Option<String>,
         amount: String,
     }, //fake
+    Split {
+        date: NaiveDate,
+        payee: String,
+        memo: Option<String>,
+        amount: String,
+        splits: Vec<SplitLine>,
+    },
+    // any other action with its cash in the account, never in the linked account.
+    Unlinked {
+        action: Box<QifAction>,
+    },
 }
 
 impl QifAction {
@@ -123,6 +180,37 @@ impl QifAction {
             Self::Sell { trade } => {
                 trade.print(output, &"Sell".to_string(), linked_account, symbols)
             }
+            Self::BuyUnlinked { trade } => trade.print(output, &"Buy".to_string(), &None, symbols),
+            Self::ReinvDiv { trade } => {
+                trade.print(output, &"ReinvDiv".to_string(), &None, symbols)
+            }
+            Self::ReinvInt { trade } => {
+                trade.print(output, &"ReinvInt".to_string(), &None, symbols)
+            }
+            Self::ReinvLg { trade } => trade.print(output, &"ReinvLg".to_string(), &None, symbols),
+            Self::ReinvSh { trade } => trade.print(output, &"ReinvSh".to_string(), &None, symbols),
+            Self::MiscInc {
+                date,
+                symbol,
+                memo,
+                amount,
+            } => {
+                let name = symbols.unwrap().lookup(symbol)?;
+                writeln!(
+                    output,
+                    "D{}/{}'{}",
+                    date.month(),
+                    date.day(),
+                    date.year() % 100
+                )?;
+                writeln!(output, "NMiscInc")?;
+                writeln!(output, "Y{}", name)?;
+                writeln!(output, "U{}", amount)?;
+                writeln!(output, "T{}", amount)?;
+                writeln!(output, "M{}", memo)?;
+                writeln!(output, "^")?;
+                Ok(())
+            }
             Self::MargInt { date, memo, amount } => {
                 writeln!(
                     output,
@@ -172,6 +260,36 @@ impl QifAction {
                 writeln!(output, "^")?;
                 Ok(())
             }
+            Self::Split {
+                date,
+                payee,
+                memo,
+                amount,
+                splits,
+            } => {
+                writeln!(
+                    output,
+                    "D{}/{}'{}",
+                    date.month(),
+                    date.day(),
+                    date.year() % 100
+                )?;
+                writeln!(output, "U{}", amount)?;
+                writeln!(output, "T{}", amount)?;
+                writeln!(output, "P{}", payee)?;
+                if let Some(memo) = memo {
+                    writeln!(output, "M{}", memo)?;
+                }
+                for split in splits {
+                    writeln!(output, "S{}", split.category)?;
+                    if let Some(memo) = &split.memo {
+                        writeln!(output, "E{}", memo)?;
+                    }
+                    writeln!(output, "${}", split.amount)?;
+                }
+                writeln!(output, "^")?;
+                Ok(())
+            }
             Self::Div {
                 date,
                 symbol,
@@ -201,6 +319,35 @@ impl QifAction {
                 writeln!(output, "^")?;
                 Ok(())
             }
+            Self::IntInc {
+                date,
+                symbol,
+                amount,
+            } => {
+                let name = symbols.unwrap().lookup(symbol)?;
+                writeln!(
+                    output,
+                    "D{}/{}'{}",
+                    date.month(),
+                    date.day(),
+                    date.year() % 100
+                )?;
+                write!(output, "NIntInc")?;
+                if linked_account.is_some() {
+                    write!(output, "X")?;
+                }
+                writeln!(output)?;
+                writeln!(output, "Y{}", name)?;
+                writeln!(output, "U{}", amount)?;
+                writeln!(output, "T{}", amount)?;
+                writeln!(output, "M{}", name)?;
+                if let Some(acctname) = linked_account {
+                    writeln!(output, "L[{}]", acctname)?
+                }
+                writeln!(output, "${}", amount)?;
+                writeln!(output, "^")?;
+                Ok(())
+            }
             Self::CGLong {
                 date,
                 symbol,
@@ -263,6 +410,8 @@ impl QifAction {
                 date,
                 symbol,
                 quantity,
+                price,
+                amount,
             } => {
                 let name = symbols.unwrap().lookup(symbol)?;
                 writeln!(
@@ -274,11 +423,73 @@ impl QifAction {
                 )?;
                 writeln!(output, "NShrsIn")?;
                 writeln!(output, "Y{}", name)?;
+                if let Some(price) = price {
+                    writeln!(output, "I{}", price)?;
+                }
                 writeln!(output, "Q{}", quantity)?;
+                if let Some(amount) = amount {
+                    writeln!(output, "U{}", amount)?;
+                    writeln!(output, "T{}", amount)?;
+                }
                 writeln!(output, "M{}", name)?;
                 writeln!(output, "^")?;
                 Ok(())
             }
+            Self::ShrsOut {
+                date,
+                symbol,
+                quantity,
+                price,
+                amount,
+            } => {
+                let name = symbols.unwrap().lookup(symbol)?;
+                writeln!(
+                    output,
+                    "D{}/{}'{}",
+                    date.month(),
+                    date.day(),
+                    date.year() % 100
+                )?;
+                writeln!(output, "NShrsOut")?;
+                writeln!(output, "Y{}", name)?;
+                if let Some(price) = price {
+                    writeln!(output, "I{}", price)?;
+                }
+                writeln!(output, "Q{}", quantity)?;
+                if let Some(amount) = amount {
+                    writeln!(output, "U{}", amount)?;
+                    writeln!(output, "T{}", amount)?;
+                }
+                writeln!(output, "M{}", name)?;
+                writeln!(output, "^")?;
+                Ok(())
+            }
+            Self::Unlinked { action } => action.print_transaction(output, &None, symbols),
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            Self::ShtSell { trade }
+            | Self::CvrShrt { trade }
+            | Self::Buy { trade }
+            | Self::Sell { trade }
+            | Self::BuyUnlinked { trade }
+            | Self::ReinvDiv { trade }
+            | Self::ReinvInt { trade }
+            | Self::ReinvLg { trade }
+            | Self::ReinvSh { trade } => trade.date,
+            Self::MargInt { date, .. }
+            | Self::Div { date, .. }
+            | Self::IntInc { date, .. }
+            | Self::MiscInc { date, .. }
+            | Self::CGLong { date, .. }
+            | Self::CGShort { date, .. }
+            | Self::ShrsIn { date, .. }
+            | Self::ShrsOut { date, .. }
+            | Self::Generic { date, .. }
+            | Self::Split { date, .. } => *date,
+            Self::Unlinked { action } => action.date(),
         }
     }
 
@@ -291,13 +502,15 @@ impl QifAction {
                 memo: _,
                 category: _,
                 amount: _,
-            }
+            } | Self::Split { .. }
         )
     }
 }
 
 pub struct QifTransactions {
     pub qif_actions: Vec<QifAction>,
+    pub account_actions: Vec<(String, Vec<QifAction>)>, // further accounts, by file name suffix.
+    pub currencies: HashMap<String, String>, // of further accounts not in the default currency.
     pub account_type: AccountType,
     pub symbols: Option<Symbols>,
 }
@@ -307,6 +520,24 @@ impl QifTransactions {
         &self,
         file_names: &FileNames,
         linked_account: &Option<String>,
+    ) -> Result<()> {
+        self.print_account_transactions(&self.qif_actions, file_names, linked_account)?;
+        for (account, qif_actions) in &self.account_actions {
+            println!("Account '{}' :", account);
+            self.print_account_transactions(
+                qif_actions,
+                &file_names.for_account(account),
+                linked_account,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_account_transactions(
+        &self,
+        qif_actions: &[QifAction],
+        file_names: &FileNames,
+        linked_account: &Option<String>,
     ) -> Result<()> {
         let mut transaction_count = 0;
         let mut linked_count = 0;
@@ -314,7 +545,7 @@ impl QifTransactions {
         let mut transactions_output: Option<File> = None;
         let mut linked_output: Option<File> = None;
 
-        for qif in &self.qif_actions {
+        for qif in qif_actions {
             if qif.linked() && linked_account.is_some() {
                 if linked_output.is_none() {
                     linked_output = Some(File::create(&file_names.linked_cash_qif)?);
@@ -332,6 +563,9 @@ impl QifTransactions {
                     let account_type_str = match self.account_type {
                         AccountType::Invest => "Invst",
                         AccountType::Cash => "Bank",
+                        AccountType::CreditCard => "CCard",
+                        // quicken imports 401(k)/403(b) transactions as investment transactions.
+                        AccountType::Retirement => "Invst",
                     };
                     writeln!(
                         transactions_output.as_ref().unwrap(),
@@ -425,6 +659,18 @@ impl QifTransactions {
                             SecurityType::MarketIndex => {
                                 writeln!(output, "TMarket Index")?;
                             }
+
+                            SecurityType::Future => {
+                                writeln!(output, "TFuture")?;
+                            }
+
+                            SecurityType::Crypto => {
+                                writeln!(output, "TCryptocurrency")?;
+                            }
+
+                            SecurityType::Bond => {
+                                writeln!(output, "TBond")?;
+                            }
                         }
                         writeln!(output, "^")?;
                     }
@@ -0,0 +1,293 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{clean_amount, format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// TreasuryDirect transaction history, for savings bonds (I and EE) and marketable
+// securities (bills, notes and bonds).
+//
+// Marketable securities are keyed by CUSIP, and savings bonds, which have none, by serial
+// number.   The quantity is the par value, so the price is the fraction of par paid.
+//
+// Bills are sold at a discount and pay par at maturity, the difference being interest
+// rather than a capital gain.   A maturing bill is sold at the cost of its purchases found
+// in the file, with the rest of the par entered as interest.
+pub struct TreasuryDirectReader;
+
+impl Reader for TreasuryDirectReader {
+    fn csv_header(&self) -> String {
+        r#"Transaction Date,Confirmation Number,Transaction Type,Product Type,CUSIP,Serial Number,Issue Date,Par Value,Amount,Interest"#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+        let mut transactions: Vec<TreasuryDirectTransaction> = Vec::new();
+        for record in rdr.deserialize::<TreasuryDirectTransaction>() {
+            if record.is_err() {
+                // some csv files are not too clean.
+                break;
+            }
+            transactions.push(record?);
+        }
+
+        // par value and cost of the bills bought, by CUSIP.
+        let mut purchases: HashMap<String, (f64, f64)> = HashMap::new();
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        // the history lists newest transactions first.
+        for transaction in transactions.iter().rev() {
+            let mut actions = transaction.to_qif_action(securities)?;
+            if transaction.is_bill() {
+                match transaction.transaction_type.as_str() {
+                    "Purchase" | "Reinvestment" => {
+                        let purchase = purchases.entry(transaction.cusip.clone()).or_default();
+                        purchase.0 += parse_amount(&transaction.par_value)?;
+                        purchase.1 += parse_amount(&transaction.amount)?;
+                    }
+                    "Maturity" => {
+                        transaction.discount_to_interest(&mut actions, &mut purchases)?;
+                    }
+                    _ => {}
+                }
+            }
+            qif_actions.append(&mut actions);
+        }
+        Ok(qif_actions)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TreasuryDirectTransaction {
+    #[serde(rename = "Transaction Date")]
+    pub date: String,
+    #[serde(rename = "Transaction Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Product Type")]
+    pub product_type: String,
+    #[serde(rename = "CUSIP")]
+    pub cusip: String,
+    #[serde(rename = "Serial Number")]
+    pub serial_number: String,
+    #[serde(rename = "Issue Date")]
+    pub issue_date: String,
+    #[serde(rename = "Par Value")]
+    pub par_value: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "Interest")]
+    pub interest: String,
+}
+
+impl TreasuryDirectTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from treasury direct: {}", &self.date))
+    }
+
+    // "Series I Savings Bond I123456789 issued 01/01/2024".
+    fn security_details(&self) -> Result<(String, String)> {
+        let symbol = if !self.cusip.is_empty() {
+            self.cusip.clone()
+        } else if !self.serial_number.is_empty() {
+            self.serial_number.clone()
+        } else {
+            return Err(eyre!(
+                "Neither CUSIP nor serial number found for {} on {}",
+                self.product_type,
+                self.date
+            ));
+        };
+        let name = format!(
+            "{} {} issued {}",
+            self.product_type, symbol, self.issue_date
+        );
+        Ok((symbol, name))
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let (symbol, name) = self.security_details()?;
+        symbols.enter_if_not_found(&symbol, &name, &SecurityType::Bond)?;
+
+        let par = parse_amount(&self.par_value)?;
+        let amount = clean_amount(&self.amount);
+        let price = if par == 0.0 {
+            "".to_string()
+        } else {
+            format_quantity(parse_amount(&amount)? / par)
+        };
+        Ok(Trade {
+            date: self.get_date()?,
+            symbol,
+            price,
+            quantity: format_quantity(par),
+            amount,
+            fees: "".to_string(),
+            memo: None,
+        })
+    }
+
+    fn is_bill(&self) -> bool {
+        self.product_type.contains("Bill") && !self.cusip.is_empty()
+    }
+
+    // the sell of a maturing bill becomes a sell at cost and the interest earned.
+    fn discount_to_interest(
+        &self,
+        actions: &mut Vec<QifAction>,
+        purchases: &mut HashMap<String, (f64, f64)>,
+    ) -> Result<()> {
+        if parse_amount(&self.interest)? != 0.0 {
+            return Ok(());
+        }
+        let par = parse_amount(&self.par_value)?;
+        let (purchased_par, purchased_cost) = match purchases.get_mut(&self.cusip) {
+            Some(purchase) if purchase.0 > 0.0 => purchase,
+            _ => {
+                println!(
+                    "No purchase found for bill {} maturing on {}, entered as sold at par.",
+                    self.cusip, self.date
+                );
+                return Ok(());
+            }
+        };
+        let cost = *purchased_cost * (par / *purchased_par).min(1.0);
+        *purchased_par -= par;
+        *purchased_cost -= cost;
+
+        let interest = parse_amount(&self.amount)? - cost;
+        for action in actions.iter_mut() {
+            if let QifAction::Sell { trade } = action {
+                trade.amount = format_amount(cost);
+                trade.price = if par == 0.0 {
+                    "".to_string()
+                } else {
+                    format_quantity(cost / par)
+                };
+            }
+        }
+        actions.push(QifAction::IntInc {
+            date: self.get_date()?,
+            symbol: self.cusip.clone(),
+            amount: format_amount(interest),
+        });
+        Ok(())
+    }
+
+    fn to_interest(&self, symbols: &mut Symbols) -> Result<Option<QifAction>> {
+        if parse_amount(&self.interest)? == 0.0 {
+            return Ok(None);
+        }
+        let (symbol, name) = self.security_details()?;
+        symbols.enter_if_not_found(&symbol, &name, &SecurityType::Bond)?;
+        Ok(Some(QifAction::IntInc {
+            date: self.get_date()?,
+            symbol,
+            amount: clean_amount(&self.interest),
+        }))
+    }
+}
+
+impl Transaction for TreasuryDirectTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let csv_type = self.transaction_type.as_str();
+        match csv_type {
+            "Purchase" | "Reinvestment" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Buy { trade });
+            }
+            // the amount is the principal, the interest earned is entered separately.
+            "Redemption" | "Maturity" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Sell { trade });
+                res.extend(self.to_interest(symbols)?);
+            }
+            "Interest" | "Interest Payment" => {
+                res.extend(self.to_interest(symbols)?);
+            }
+            _ => {
+                println!(
+                    "Unrecognized transaction type found in .CSV : \"{}\".",
+                    csv_type
+                );
+                println!("{:#?}", self);
+                println!();
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_treasury_direct() -> Result<()> {
+        let csv = TreasuryDirectReader {}.csv_header()
+            + "\n"
+            + "07/16/2024,C3,Redemption,Series I Savings Bond,,I123456789,01/01/2024,\"$1,000.00\",\"$1,000.00\",$21.40\n"
+            + "07/11/2024,C2,Maturity,26-Week Bill,912797GK7,,01/11/2024,\"$1,000.00\",\"$1,000.00\",\n"
+            + "01/11/2024,C1,Purchase,26-Week Bill,912797GK7,,01/11/2024,\"$1,000.00\",$974.50,\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions =
+            TreasuryDirectReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        assert_eq!(actions.len(), 5);
+
+        match &actions[0] {
+            QifAction::Buy { trade } => {
+                assert_eq!(trade.symbol, "912797GK7");
+                assert_eq!(trade.quantity, "1000");
+                assert_eq!(trade.price, "0.9745");
+                assert_eq!(trade.amount, "974.50");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+        // the discount of the bill is interest, the bill is sold at cost.
+        match (&actions[1], &actions[2]) {
+            (QifAction::Sell { trade }, QifAction::IntInc { symbol, amount, .. }) => {
+                assert_eq!(trade.symbol, "912797GK7");
+                assert_eq!(trade.amount, "974.50");
+                assert_eq!(trade.price, "0.9745");
+                assert_eq!(symbol, "912797GK7");
+                assert_eq!(amount, "25.50");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        match (&actions[3], &actions[4]) {
+            (QifAction::Sell { trade }, QifAction::IntInc { symbol, amount, .. }) => {
+                assert_eq!(trade.symbol, "I123456789");
+                assert_eq!(symbol, "I123456789");
+                assert_eq!(amount, "21.40");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        let symbols = symbols.unwrap();
+        assert_eq!(
+            symbols.lookup(&"I123456789".to_string())?,
+            "Series I Savings Bond I123456789 issued 01/01/2024"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::csv_reader::*;
+use crate::security::SecurityType;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// Vanguard's transaction history starts with a holdings section which has its own header.
+// Only the header of the transactions section that follows is registered, so the holdings
+// section is skipped while looking for it.
+pub struct VanguardReader;
+
+impl Reader for VanguardReader {
+    fn csv_header(&self) -> String {
+        r#"Account Number,Trade Date,Settlement Date,Transaction Type,Transaction Description,Investment Name,Symbol,Shares,Share Price,Principal Amount,Commission Fees,Net Amount,Accrued Interest,Account Type,"#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<VanguardTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VanguardTransaction {
+    #[serde(rename = "Trade Date")]
+    pub date: String,
+    #[serde(rename = "Transaction Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Transaction Description")]
+    pub description: String,
+    #[serde(rename = "Investment Name")]
+    pub investment_name: String,
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Shares")]
+    pub shares: String,
+    #[serde(rename = "Share Price")]
+    pub price: String,
+    #[serde(rename = "Commission Fees")]
+    pub fees: String,
+    #[serde(rename = "Net Amount")]
+    pub amount: String,
+}
+
+impl VanguardTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(&self.date, "%m/%d/%Y"))
+            .with_context(|| format!("Could not parse date from vanguard: {}", &self.date))
+    }
+
+    // Mutual fund tickers are five letters ending in X, for example VTSAX.
+    // Everything else vanguard holds (ETFs, stocks) is entered as a stock.
+    fn security_details(&self) -> (String, String, SecurityType) {
+        let symbol = self.symbol.trim().to_string();
+        let name = self.investment_name.trim().to_string();
+        let security_type = if symbol.len() == 5 && symbol.ends_with('X') {
+            SecurityType::MutualFund
+        } else {
+            SecurityType::Stock
+        };
+        (symbol, name, security_type)
+    }
+
+    fn to_trade(&self, symbols: &mut Symbols) -> Result<Trade> {
+        let (symbol, name, security_type) = self.security_details();
+        symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+
+        // vanguard signs shares and amount by direction but quicken wants them positive.
+        let res = Trade {
+            date: self.get_date()?,
+            symbol,
+            price: self.price.clone(),
+            quantity: self.shares.trim_start_matches('-').to_string(),
+            amount: self.amount.trim_start_matches('-').to_string(),
+            fees: self.fees.clone(),
+            memo: None,
+        };
+        Ok(res)
+    }
+
+    fn is_zero_or_empty(field: &str) -> bool {
+        let trimmed = field.trim();
+        trimmed.is_empty() || trimmed.parse::<f64>().is_ok_and(|v| v == 0.0)
+    }
+}
+
+impl Transaction for VanguardTransaction {
+    fn to_qif_action(&self, opt_symbols: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        let symbols = opt_symbols
+            .as_mut()
+            .ok_or(eyre!("Expected symbols but none provided."))?;
+        let mut res: Vec<QifAction> = Vec::new();
+
+        let csv_type = self.transaction_type.as_str();
+        match csv_type {
+            "Buy" | "Reinvestment" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Buy { trade });
+            }
+            "Sell" => {
+                let trade = self.to_trade(symbols)?;
+                res.push(QifAction::Sell { trade });
+            }
+            "Dividend" => {
+                let (symbol, name, security_type) = self.security_details();
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::Div {
+                    date: self.get_date()?,
+                    symbol,
+                    amount: self.amount.clone(),
+                });
+            }
+            "Capital gain (LT)" => {
+                let (symbol, name, security_type) = self.security_details();
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::CGLong {
+                    date: self.get_date()?,
+                    symbol,
+                    amount: self.amount.clone(),
+                });
+            }
+            "Capital gain (ST)" => {
+                let (symbol, name, security_type) = self.security_details();
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::CGShort {
+                    date: self.get_date()?,
+                    symbol,
+                    amount: self.amount.clone(),
+                });
+            }
+            "Conversion (incoming)" | "Transfer (incoming)" => {
+                let (symbol, name, security_type) = self.security_details();
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::ShrsIn {
+                    date: self.get_date()?,
+                    symbol,
+                    quantity: self.shares.trim_start_matches('-').to_string(),
+                    price: None,
+                    amount: None,
+                });
+            }
+            "Conversion (outgoing)" | "Transfer (outgoing)" => {
+                let (symbol, name, security_type) = self.security_details();
+                symbols.enter_if_not_found(&symbol, &name, &security_type)?;
+                res.push(QifAction::ShrsOut {
+                    date: self.get_date()?,
+                    symbol,
+                    quantity: self.shares.trim_start_matches('-').to_string(),
+                    price: None,
+                    amount: None,
+                });
+            }
+            "Sweep in" | "Sweep out" => {
+                // Sweeps move cash into and out of the settlement fund.   Quicken treats the
+                // settlement fund as the cash in the account, so there is nothing to record.
+            }
+            _ => {
+                if Self::is_zero_or_empty(&self.shares) {
+                    res.push(QifAction::Generic {
+                        date: self.get_date()?,
+                        payee: self.description.clone(),
+                        memo: Some(self.transaction_type.clone()),
+                        category: None,
+                        amount: self.amount.clone(),
+                    });
+                } else {
+                    let message = "Unrecognized transaction type found in .CSV file : ".to_string()
+                        + csv_type;
+                    return Err(eyre!(message));
+                }
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_vanguard() -> Result<()> {
+        let csv = VanguardReader {}.csv_header()
+            + "\n"
+            + "12345678,2024-01-18,2024-01-19,Transfer (outgoing),Transfer,Vanguard Total Stock Market ETF,VTI,-5.0000,0.0,0.0,0.0,0.0,0.0,CASH,\n"
+            + "12345678,2024-01-17,2024-01-17,Transfer (incoming),Transfer,Vanguard Total Stock Market Index Fund Admiral,VTSAX,12.5000,0.0,0.0,0.0,0.0,0.0,CASH,\n"
+            + "12345678,2024-01-16,2024-01-16,Reinvestment,Dividend Reinvestment,Vanguard Total Stock Market Index Fund Admiral,VTSAX,0.2500,110.00,-27.50,0.0,-27.50,0.0,CASH,\n"
+            + "12345678,2024-01-15,2024-01-17,Buy,Buy,Vanguard Total Stock Market ETF,VTI,10.0000,230.50,-2305.00,0.0,-2305.00,0.0,CASH,\n";
+        let mut symbols = Some(Symbols::new(&PathBuf::from("test_data/securities.txt"))?);
+        let actions = VanguardReader {}.to_transactions(&mut Cursor::new(csv), &mut symbols)?;
+        match &actions[..] {
+            [QifAction::Buy { trade: buy }, QifAction::Buy { trade: reinvest }, QifAction::ShrsIn {
+                symbol: symbol_in,
+                quantity: quantity_in,
+                ..
+            }, QifAction::ShrsOut {
+                symbol: symbol_out,
+                quantity: quantity_out,
+                ..
+            }] => {
+                assert_eq!(buy.symbol, "VTI");
+                assert_eq!(buy.quantity, "10.0000");
+                assert_eq!(buy.amount, "2305.00");
+                assert_eq!(reinvest.symbol, "VTSAX");
+                assert_eq!(reinvest.amount, "27.50");
+                assert_eq!(symbol_in, "VTSAX");
+                assert_eq!(quantity_in, "12.5000");
+                assert_eq!(symbol_out, "VTI");
+                assert_eq!(quantity_out, "5.0000");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        // five letters ending in X is a mutual fund, the rest are entered as stocks.
+        let symbols = symbols.unwrap();
+        assert_eq!(symbols.lookup_type("VTSAX"), Some(SecurityType::MutualFund));
+        assert_eq!(symbols.lookup_type("VTI"), Some(SecurityType::Stock));
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, parse_amount};
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// Venmo account statement.
+//
+// The real header follows a couple of title lines, which find_matching_line skips.   The
+// first row after the header only carries the beginning balance and the last one the ending
+// balance, with a disclaimer after it.
+//
+// The statement doesn't say whose it is, but the owner is on one side of nearly every
+// transaction, so the most frequent From/To name is taken as the owner and the other side
+// as the payee.   Payments funded from a bank or card don't go through the Venmo balance,
+// so they get an offsetting entry from the funding source.
+pub struct VenmoReader;
+
+impl Reader for VenmoReader {
+    fn csv_header(&self) -> String {
+        r#",ID,Datetime,Type,Status,Note,From,To,Amount (total),Amount (tip),Amount (tax),Amount (fee),Tax Rate,Tax Exempt,Funding Source,Destination,Beginning Balance,Ending Balance,Statement Period Venmo Fees,Terminal Location,Year to Date Venmo Fees,Disclaimer"#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%dT%H:%M:%S".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        _securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+
+        let mut beginning_balance: Option<f64> = None;
+        let mut ending_balance: Option<f64> = None;
+        let mut transactions: Vec<VenmoTransaction> = Vec::new();
+        for record in rdr.deserialize::<VenmoTransaction>() {
+            let transaction = match record {
+                Ok(transaction) => transaction,
+                // the disclaimer at the end is not a proper row.
+                Err(_) => break,
+            };
+            if transaction.id.is_empty() {
+                if !transaction.beginning_balance.is_empty() && beginning_balance.is_none() {
+                    beginning_balance = Some(parse_venmo_amount(&transaction.beginning_balance)?);
+                }
+                if !transaction.ending_balance.is_empty() {
+                    ending_balance = Some(parse_venmo_amount(&transaction.ending_balance)?);
+                }
+                continue;
+            }
+            transactions.push(transaction);
+        }
+
+        let owner = find_owner(&transactions);
+        let mut qif_actions: Vec<QifAction> = Vec::new();
+        for transaction in &transactions {
+            qif_actions.append(&mut transaction.to_qif_action(&owner)?);
+        }
+
+        if let (Some(beginning), Some(ending)) = (beginning_balance, ending_balance) {
+            check_ending_balance(beginning, ending, &qif_actions)?;
+        }
+        Ok(qif_actions)
+    }
+}
+
+// venmo writes amounts as "- $25.00" and "+ $1,000.00".
+fn parse_venmo_amount(amount: &str) -> Result<f64> {
+    parse_amount(&amount.replace(' ', ""))
+}
+
+fn find_owner(transactions: &[VenmoTransaction]) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for transaction in transactions {
+        for name in [&transaction.from, &transaction.to] {
+            if !name.is_empty() {
+                *counts.entry(name.as_str()).or_default() += 1;
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default()
+}
+
+fn check_ending_balance(beginning: f64, ending: f64, qif_actions: &[QifAction]) -> Result<()> {
+    let mut computed = beginning;
+    for action in qif_actions {
+        if let QifAction::Generic { amount, .. } = action {
+            computed += parse_amount(amount)?;
+        }
+    }
+    if format_amount(computed) != format_amount(ending) {
+        println!(
+            "Computed ending balance {} does not match statement ending balance {}.",
+            format_amount(computed),
+            format_amount(ending)
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VenmoTransaction {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "Datetime")]
+    pub datetime: String,
+    #[serde(rename = "Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Note")]
+    pub note: String,
+    #[serde(rename = "From")]
+    pub from: String,
+    #[serde(rename = "To")]
+    pub to: String,
+    #[serde(rename = "Amount (total)")]
+    pub amount: String,
+    #[serde(rename = "Funding Source")]
+    pub funding_source: String,
+    #[serde(rename = "Destination")]
+    pub destination: String,
+    #[serde(rename = "Beginning Balance")]
+    pub beginning_balance: String,
+    #[serde(rename = "Ending Balance")]
+    pub ending_balance: String,
+}
+
+impl VenmoTransaction {
+    // datetimes look like "2024-01-15T18:23:45".
+    fn get_date(&self) -> Result<NaiveDate> {
+        let date = self.datetime.split('T').next().unwrap_or("");
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from venmo: {}", &self.datetime))
+    }
+
+    fn payee(&self, owner: &str) -> String {
+        let other = if self.from == owner {
+            &self.to
+        } else {
+            &self.from
+        };
+        if !other.is_empty() {
+            other.clone()
+        } else if !self.destination.is_empty() {
+            self.destination.clone()
+        } else {
+            self.transaction_type.clone()
+        }
+    }
+
+    fn is_externally_funded(&self, amount: f64) -> bool {
+        amount < 0.0 && !self.funding_source.is_empty() && self.funding_source != "Venmo balance"
+    }
+
+    fn to_qif_action(&self, owner: &str) -> Result<Vec<QifAction>> {
+        let mut res: Vec<QifAction> = Vec::new();
+
+        match self.status.as_str() {
+            "Complete" | "Issued" => {}
+            "Pending" | "Failed" | "Canceled" | "Cancelled" => return Ok(res),
+            status => {
+                println!("Unrecognized status found in .CSV : \"{}\".", status);
+            }
+        };
+
+        let date = self.get_date()?;
+        let amount = parse_venmo_amount(&self.amount)?;
+        let memo = if self.note.is_empty() {
+            Some(self.transaction_type.clone())
+        } else {
+            Some(self.note.clone())
+        };
+        res.push(QifAction::Generic {
+            date,
+            payee: self.payee(owner),
+            memo,
+            category: None,
+            amount: format_amount(amount),
+        });
+        if self.is_externally_funded(amount) {
+            res.push(QifAction::Generic {
+                date,
+                payee: self.funding_source.clone(),
+                memo: Some(format!("Funding : {}", self.payee(owner))),
+                category: None,
+                amount: format_amount(-amount),
+            });
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_venmo() -> Result<()> {
+        // the title lines before the header are skipped by find_matching_line.
+        let csv = VenmoReader {}.csv_header()
+            + "\n"
+            + ",,,,,,,,,,,,,,,,$10.00,,,,,\n"
+            + ",1001,2024-01-15T18:23:45,Payment,Complete,Dinner,John Smith,Jane Doe,- $25.00,,0,,0,,Visa Debit *1234,,,,,Venmo,,\n"
+            + ",1002,2024-01-16T09:00:00,Charge,Complete,Rent,John Smith,Bob Jones,\"+ $1,000.00\",,0,,0,,,Venmo balance,,,,Venmo,,\n"
+            + ",1003,2024-01-17T09:00:00,Payment,Complete,Lunch,Bob Jones,John Smith,+ $5.00,,0,,0,,,Venmo balance,,,,Venmo,,\n"
+            + ",1004,2024-01-18T09:00:00,Standard Transfer,Issued,,,,- $900.00,,0,,0,,,Chase Checking *5678,,,,Venmo,,\n"
+            + ",,,,,,,,,,,,,,,,,$115.00,$0.00,,$0.00,\"In case of errors or questions about your electronic transfers, ...\"\n";
+        let actions = VenmoReader {}.to_transactions(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(actions.len(), 5);
+
+        let generic = |action: &QifAction| match action {
+            QifAction::Generic { payee, amount, .. } => (payee.clone(), amount.clone()),
+            other => panic!("unexpected action: {:?}", other),
+        };
+        assert_eq!(
+            generic(&actions[0]),
+            ("Jane Doe".to_string(), "-25.00".to_string())
+        );
+        assert_eq!(
+            generic(&actions[1]),
+            ("Visa Debit *1234".to_string(), "25.00".to_string())
+        );
+        assert_eq!(
+            generic(&actions[2]),
+            ("Bob Jones".to_string(), "1000.00".to_string())
+        );
+        assert_eq!(
+            generic(&actions[3]),
+            ("Bob Jones".to_string(), "5.00".to_string())
+        );
+        assert_eq!(
+            generic(&actions[4]),
+            ("Chase Checking *5678".to_string(), "-900.00".to_string())
+        );
+        Ok(())
+    }
+}
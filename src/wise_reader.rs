@@ -0,0 +1,319 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+use std::result::Result::Ok;
+
+use crate::amounts::{format_amount, format_quantity, parse_amount};
+use crate::csv_reader::*;
+use crate::symbols::Symbols;
+use crate::transactions_qif::*;
+
+// Wise transaction history, which holds every currency balance of the account together.
+//
+// The transactions are split by currency, with one output .qif per balance.   A conversion
+// between two balances of the account (direction NEUTRAL) is entered in both, with the
+// exchange rate and fee in the memo.
+pub struct WiseReader;
+
+impl Reader for WiseReader {
+    fn csv_header(&self) -> String {
+        r#"ID,Status,Direction,Created on,Finished on,Source fee amount,Source fee currency,Target fee amount,Target fee currency,Source name,Source amount (after fees),Source currency,Target name,Target amount (after fees),Target currency,Exchange rate,Reference,Batch,Created by,Category,Note"#
+            .to_string()
+    }
+
+    fn date_format(&self) -> String {
+        "%Y-%m-%d %H:%M:%S".to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        Ok(self
+            .to_transactions_by_account(bufreader, securities)?
+            .into_iter()
+            .flat_map(|(_, actions)| actions)
+            .collect())
+    }
+
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        _securities: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bufreader);
+        let mut entries: Vec<(String, Vec<QifAction>)> = Vec::new();
+        for record in rdr.deserialize::<WiseTransaction>() {
+            if record.is_err() {
+                // some csv files are not too clean.
+                break;
+            }
+            for (currency, action) in record?.to_qif_actions()? {
+                entries.push((currency, vec![action]));
+            }
+        }
+        Ok(group_by_account(entries))
+    }
+
+    // each account is the balance of the currency it is named after.
+    fn account_currency(&self, account: &str) -> Option<String> {
+        Some(account.to_string())
+    }
+}
+
+// fees without a currency are in the currency of their side of the transaction.
+fn fee_currency<'a>(currency: &'a str, default_currency: &'a str) -> &'a str {
+    if currency.is_empty() {
+        default_currency
+    } else {
+        currency
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WiseTransaction {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Direction")]
+    pub direction: String,
+    #[serde(rename = "Created on")]
+    pub created_on: String,
+    #[serde(rename = "Source fee amount")]
+    pub source_fee: String,
+    #[serde(rename = "Source fee currency")]
+    pub source_fee_currency: String,
+    #[serde(rename = "Target fee amount")]
+    pub target_fee: String,
+    #[serde(rename = "Target fee currency")]
+    pub target_fee_currency: String,
+    #[serde(rename = "Source name")]
+    pub source_name: String,
+    #[serde(rename = "Source amount (after fees)")]
+    pub source_amount: String,
+    #[serde(rename = "Source currency")]
+    pub source_currency: String,
+    #[serde(rename = "Target name")]
+    pub target_name: String,
+    #[serde(rename = "Target amount (after fees)")]
+    pub target_amount: String,
+    #[serde(rename = "Target currency")]
+    pub target_currency: String,
+    #[serde(rename = "Exchange rate")]
+    pub exchange_rate: String,
+    #[serde(rename = "Reference")]
+    pub reference: String,
+}
+
+impl WiseTransaction {
+    // dates look like "2024-01-15 10:00:05".
+    fn get_date(&self) -> Result<NaiveDate> {
+        let date = self.created_on.get(..10).unwrap_or("");
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .with_context(|| format!("Could not parse date from wise: {}", &self.created_on))
+    }
+
+    // everything taken from the source balance, the fee included when in the source
+    // currency.   The target amount is already net of the target fee.
+    fn source_total(&self) -> Result<f64> {
+        let amount = parse_amount(&self.source_amount)?;
+        let fee = parse_amount(&self.source_fee)?;
+        if fee != 0.0
+            && fee_currency(&self.source_fee_currency, &self.source_currency)
+                != self.source_currency
+        {
+            println!(
+                "Fee of {} {} not in {}, not taken from the balance of transaction {}.",
+                format_amount(fee),
+                self.source_fee_currency,
+                self.source_currency,
+                self.id
+            );
+            return Ok(amount);
+        }
+        Ok(amount + fee)
+    }
+
+    fn is_conversion(&self) -> bool {
+        self.source_currency != self.target_currency
+    }
+
+    fn conversion_memo(&self) -> Result<String> {
+        let mut memo = format!(
+            "{} {} to {} {} at {}",
+            format_amount(parse_amount(&self.source_amount)?),
+            self.source_currency,
+            format_amount(parse_amount(&self.target_amount)?),
+            self.target_currency,
+            format_quantity(parse_amount(&self.exchange_rate)?)
+        );
+        for (fee, currency, default_currency) in [
+            (
+                &self.source_fee,
+                &self.source_fee_currency,
+                &self.source_currency,
+            ),
+            (
+                &self.target_fee,
+                &self.target_fee_currency,
+                &self.target_currency,
+            ),
+        ] {
+            if parse_amount(fee)? != 0.0 {
+                memo += &format!(
+                    ", fee {} {}",
+                    format_amount(parse_amount(fee)?),
+                    fee_currency(currency, default_currency)
+                );
+            }
+        }
+        Ok(memo)
+    }
+
+    fn memo(&self) -> Result<Option<String>> {
+        let mut parts: Vec<String> = Vec::new();
+        if !self.reference.is_empty() {
+            parts.push(self.reference.clone());
+        }
+        if self.is_conversion() {
+            parts.push(self.conversion_memo()?);
+        }
+        Ok(if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" : "))
+        })
+    }
+
+    // each action is paired with the currency of the balance it belongs to.
+    fn to_qif_actions(&self) -> Result<Vec<(String, QifAction)>> {
+        let mut res: Vec<(String, QifAction)> = Vec::new();
+
+        if self.status != "COMPLETED" {
+            println!(
+                "Skipping {} transaction {}.",
+                self.status.to_lowercase(),
+                self.id
+            );
+            return Ok(res);
+        }
+
+        let date = self.get_date()?;
+        let memo = self.memo()?;
+        let source = QifAction::Generic {
+            date,
+            payee: self.target_name.clone(),
+            memo: memo.clone(),
+            category: None,
+            amount: format_amount(-self.source_total()?),
+        };
+        let target = QifAction::Generic {
+            date,
+            payee: self.source_name.clone(),
+            memo,
+            category: None,
+            amount: format_amount(parse_amount(&self.target_amount)?),
+        };
+
+        match self.direction.as_str() {
+            "OUT" => res.push((self.source_currency.clone(), source)),
+            "IN" => res.push((self.target_currency.clone(), target)),
+            "NEUTRAL" => {
+                res.push((self.source_currency.clone(), source));
+                res.push((self.target_currency.clone(), target));
+            }
+            direction => {
+                println!("Unrecognized direction found in .CSV : \"{}\".", direction);
+                println!("{:#?}", self);
+                println!();
+            }
+        };
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_wise() -> Result<()> {
+        let csv = WiseReader {}.csv_header()
+            + "\n"
+            + "T3,COMPLETED,OUT,2024-01-17 10:00:00,2024-01-17 10:00:01,0.00,EUR,,,Jane Doe,50.00,EUR,Landlord,50.00,EUR,1.00,Rent,,Jane Doe,General,\n"
+            + "T2,COMPLETED,NEUTRAL,2024-01-16 10:00:00,2024-01-16 10:00:01,0.50,USD,,,Jane Doe,99.50,USD,Jane Doe,91.54,EUR,0.92,,,Jane Doe,Money added,\n"
+            + "T1,COMPLETED,IN,2024-01-15 10:00:00,2024-01-15 10:00:01,0.00,USD,,,ACME Inc,1000.00,USD,Jane Doe,1000.00,USD,1.00,Salary,,ACME Inc,Money added,\n"
+            + "T0,CANCELLED,OUT,2024-01-14 10:00:00,,0.00,GBP,,,Jane Doe,10.00,GBP,Bob,10.00,GBP,1.00,,,Jane Doe,General,\n";
+        let accounts =
+            WiseReader {}.to_transactions_by_account(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(accounts.len(), 2);
+
+        let (currency, eur) = &accounts[0];
+        assert_eq!(currency, "EUR");
+        assert_eq!(eur.len(), 2);
+        match &eur[0] {
+            QifAction::Generic { memo, amount, .. } => {
+                assert_eq!(
+                    memo.as_deref(),
+                    Some("99.50 USD to 91.54 EUR at 0.92, fee 0.50 USD")
+                );
+                assert_eq!(amount, "91.54");
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+
+        let (currency, usd) = &accounts[1];
+        assert_eq!(currency, "USD");
+        assert_eq!(
+            WiseReader {}.account_currency(currency),
+            Some("USD".to_string())
+        );
+        match (&usd[0], &usd[1]) {
+            (
+                QifAction::Generic { payee, amount, .. },
+                QifAction::Generic {
+                    amount: converted, ..
+                },
+            ) => {
+                assert_eq!(payee, "ACME Inc");
+                assert_eq!(amount, "1000.00");
+                assert_eq!(converted, "-100.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_wise_fees() -> Result<()> {
+        let csv = WiseReader {}.csv_header()
+            + "\n"
+            + "T5,COMPLETED,NEUTRAL,2024-02-01 10:00:00,2024-02-01 10:00:01,1.00,EUR,0.20,EUR,Jane Doe,50.00,USD,Jane Doe,45.80,EUR,0.92,,,Jane Doe,Money added,\n";
+        let accounts =
+            WiseReader {}.to_transactions_by_account(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(accounts.len(), 2);
+        match (&accounts[0].1[..], &accounts[1].1[..]) {
+            (
+                [QifAction::Generic {
+                    memo, amount: eur, ..
+                }],
+                [QifAction::Generic { amount: usd, .. }],
+            ) => {
+                assert_eq!(
+                    memo.as_deref(),
+                    Some("50.00 USD to 45.80 EUR at 0.92, fee 1.00 EUR, fee 0.20 EUR")
+                );
+                assert_eq!(eur, "45.80");
+                // the source fee is in euros, not taken from the dollar balance.
+                assert_eq!(usd, "-50.00");
+            }
+            other => panic!("unexpected accounts: {:?}", other),
+        }
+        Ok(())
+    }
+}
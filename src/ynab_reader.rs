@@ -0,0 +1,193 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use stable_eyre::eyre::*;
+use std::io::BufRead;
+
+use crate::amounts::parse_amount;
+use crate::csv_reader::*;
+use crate::split_amount::*;
+use crate::symbols::Symbols;
+use crate::transaction::*;
+use crate::transactions_qif::*;
+
+// YNAB register export, holding every budget account.   The transactions are split by
+// account name, with one output .qif per account.
+//
+// Categories are written as "Group:Category", and transfers between budget accounts
+// (payee "Transfer : Savings") as transfers to the other account ("[Savings]").   YNAB
+// lists a transfer in both accounts, only the outflow is kept, quicken enters the other
+// side in the account it is transferred to.
+pub struct YnabReader;
+
+impl Reader for YnabReader {
+    fn csv_header(&self) -> String {
+        r#""Account","Flag","Date","Payee","Category Group/Category","Category Group","Category","Memo","Outflow","Inflow","Cleared""#
+            .to_string()
+    }
+
+    fn to_transactions(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<QifAction>> {
+        <dyn Reader>::from_csv::<YnabTransaction>(bufreader, securities)
+    }
+
+    fn to_transactions_by_account(
+        &self,
+        bufreader: &mut dyn BufRead,
+        securities: &mut Option<Symbols>,
+    ) -> Result<Vec<(String, Vec<QifAction>)>> {
+        <dyn Reader>::from_csv_by_account::<YnabTransaction>(bufreader, securities)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct YnabTransaction {
+    #[serde(rename = "Account")]
+    pub account: String,
+    #[serde(rename = "Date")]
+    pub date: String,
+    #[serde(rename = "Payee")]
+    pub payee: String,
+    #[serde(rename = "Category Group")]
+    pub category_group: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+    #[serde(rename = "Memo")]
+    pub memo: String,
+    #[serde(rename = "Outflow")]
+    pub outflow: String,
+    #[serde(rename = "Inflow")]
+    pub inflow: String,
+}
+
+impl SplitAmountTransaction for YnabTransaction {
+    fn get_date(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%m/%d/%Y")
+            .with_context(|| format!("Could not parse date from ynab: {}", &self.date))
+    }
+
+    fn payee(&self) -> String {
+        self.payee.clone()
+    }
+
+    fn debit(&self) -> &str {
+        &self.outflow
+    }
+
+    fn credit(&self) -> &str {
+        &self.inflow
+    }
+
+    fn memo(&self) -> Option<String> {
+        if self.memo.is_empty() {
+            None
+        } else {
+            Some(self.memo.clone())
+        }
+    }
+
+    fn category(&self) -> Option<String> {
+        if let Some(account) = self.transfer_account() {
+            return Some(format!("[{}]", account));
+        }
+        match (self.category_group.is_empty(), self.category.is_empty()) {
+            (_, true) => None,
+            (true, false) => Some(self.category.clone()),
+            (false, false) => Some(format!("{}:{}", self.category_group, self.category)),
+        }
+    }
+}
+
+impl YnabTransaction {
+    fn transfer_account(&self) -> Option<&str> {
+        self.payee.strip_prefix("Transfer : ")
+    }
+}
+
+impl Transaction for YnabTransaction {
+    fn to_qif_action(&self, _securities: &mut Option<Symbols>) -> Result<Vec<QifAction>> {
+        if let Some(account) = self.transfer_account() {
+            if parse_amount(&signed_amount(&self.outflow, &self.inflow)?)? > 0.0 {
+                println!(
+                    "Skipping transfer from {} into {}, entered with the outflow.",
+                    account, self.account
+                );
+                return Ok(Vec::new());
+            }
+        }
+        to_generic_action(self)
+    }
+}
+
+impl AccountTransaction for YnabTransaction {
+    fn account(&self) -> String {
+        self.account.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ynab() -> Result<()> {
+        let csv = YnabReader {}.csv_header()
+            + "\n"
+            + "\"Checking\",\"\",\"01/16/2024\",\"Transfer : Savings\",\"\",\"\",\"\",\"\",$100.00,$0.00,\"Cleared\"\n"
+            + "\"Checking\",\"\",\"01/15/2024\",\"Grocer\",\"Everyday: Groceries\",\"Everyday\",\"Groceries\",\"milk\",$12.34,$0.00,\"Cleared\"\n";
+        let accounts =
+            YnabReader {}.to_transactions_by_account(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, "Checking");
+        match &accounts[0].1[..] {
+            [QifAction::Generic {
+                category, amount, ..
+            }, QifAction::Generic {
+                category: transfer, ..
+            }] => {
+                assert_eq!(category.as_deref(), Some("Everyday:Groceries"));
+                assert_eq!(amount, "-12.34");
+                assert_eq!(transfer.as_deref(), Some("[Savings]"));
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ynab_transfer() -> Result<()> {
+        let csv = YnabReader {}.csv_header()
+            + "\n"
+            + "\"Savings\",\"\",\"01/16/2024\",\"Transfer : Checking\",\"\",\"\",\"\",\"\",$0.00,$100.00,\"Cleared\"\n"
+            + "\"Checking\",\"\",\"01/16/2024\",\"Transfer : Savings\",\"\",\"\",\"\",\"\",$100.00,$0.00,\"Cleared\"\n"
+            + "\"Savings\",\"\",\"01/15/2024\",\"Bank\",\"Income: Interest\",\"Income\",\"Interest\",\"\",$0.00,$1.25,\"Cleared\"\n";
+        let accounts =
+            YnabReader {}.to_transactions_by_account(&mut Cursor::new(csv), &mut None)?;
+        assert_eq!(accounts.len(), 2);
+
+        // the transfer is entered once, from checking.
+        let (name, checking) = &accounts[0];
+        assert_eq!(name, "Checking");
+        match &checking[..] {
+            [QifAction::Generic {
+                category, amount, ..
+            }] => {
+                assert_eq!(category.as_deref(), Some("[Savings]"));
+                assert_eq!(amount, "-100.00");
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        let (name, savings) = &accounts[1];
+        assert_eq!(name, "Savings");
+        match &savings[..] {
+            [QifAction::Generic { category, .. }] => {
+                assert_eq!(category.as_deref(), Some("Income:Interest"));
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        Ok(())
+    }
+}